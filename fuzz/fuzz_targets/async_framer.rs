@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use sdtx_fuzz::Input;
+
+
+fuzz_target!(|input: Input| {
+    let expected = input.reference();
+
+    assert_eq!(input.read_stream(), expected);
+    assert_eq!(input.read_async(), expected);
+    assert_eq!(input.read_blocking(), expected);
+});
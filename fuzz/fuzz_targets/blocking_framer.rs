@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use sdtx_fuzz::Input;
+
+
+fuzz_target!(|input: Input| {
+    assert_eq!(input.read_blocking(), input.reference());
+});
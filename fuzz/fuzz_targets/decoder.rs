@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use sdtx_fuzz::Input;
+
+
+fuzz_target!(|input: (Input, u16)| {
+    let (input, max_payload_len) = input;
+    let max_payload_len = max_payload_len as usize;
+
+    assert_eq!(input.read_decoder(max_payload_len), input.reference_with(max_payload_len));
+});
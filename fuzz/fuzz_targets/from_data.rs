@@ -0,0 +1,32 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use sdtx::{DecodeMode, Event};
+
+
+fuzz_target!(|input: (u16, &[u8])| {
+    let (code, data) = input;
+
+    if data.len() > u16::MAX as usize {
+        return;
+    }
+
+    let event = Event::from_data(code, data);
+
+    // encoding must reproduce the input exactly
+    let bytes = event.to_bytes();
+    assert_eq!(&bytes[0..2], &(data.len() as u16).to_ne_bytes());
+    assert_eq!(&bytes[2..4], &code.to_ne_bytes());
+    assert_eq!(&bytes[4..], data);
+
+    // and so decoding it again must give back the same event
+    assert_eq!(Event::from_data(event.code(), &bytes[4..]), event);
+    assert_eq!(event.normalized(), event);
+
+    // strict decoding fails exactly for events lossy decoding skips
+    let strict = DecodeMode::Strict.apply(event.clone());
+    let lossy = DecodeMode::Lossy.apply(event.clone()).unwrap();
+    assert_eq!(strict.is_err(), lossy.is_none());
+    assert_eq!(event.validate().is_err(), lossy.is_none());
+});
@@ -0,0 +1,199 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::task::ArcWake;
+use futures::StreamExt;
+use arbitrary::Arbitrary;
+
+use sdtx::event::DEFAULT_MAX_PAYLOAD_LEN;
+use sdtx::testing::ChunkedReader;
+use sdtx::{uapi, DecodeMode, Device, DtxBackend, Event, EventDecoder, ProtocolError};
+
+
+/// Outcome of reading a single event, comparable between readers.
+pub type Item = Result<Event, ProtocolError>;
+
+
+#[derive(Debug, Clone, Copy, Arbitrary)]
+enum Mode {
+    Lenient,
+    Strict,
+    Lossy,
+}
+
+impl From<Mode> for DecodeMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Lenient => DecodeMode::Lenient,
+            Mode::Strict  => DecodeMode::Strict,
+            Mode::Lossy   => DecodeMode::Lossy,
+        }
+    }
+}
+
+
+/// Raw event data, as returned by the device, together with how it is split
+/// into reads.
+#[derive(Debug, Arbitrary)]
+pub struct Input {
+    data: Vec<u8>,
+
+    /// Sizes of the reads, repeated as needed with a maximum-sized read in
+    /// between. A size of zero makes the async reader return `Pending` once.
+    chunks: Vec<u8>,
+
+    mode: Mode,
+}
+
+impl Input {
+    /// Decode the data directly via `Event::from_data`, dropping any
+    /// incomplete event at the end.
+    pub fn reference(&self) -> Vec<Item> {
+        self.reference_with(DEFAULT_MAX_PAYLOAD_LEN)
+    }
+
+    pub fn reference_with(&self, max_payload_len: usize) -> Vec<Item> {
+        const HEADER_LEN: usize = std::mem::size_of::<uapi::EventHeader>();
+
+        let mode = DecodeMode::from(self.mode);
+        let mut data = self.data.as_slice();
+        let mut items = Vec::new();
+
+        while data.len() >= HEADER_LEN {
+            let length = u16::from_ne_bytes([data[0], data[1]]) as usize;
+            let code = u16::from_ne_bytes([data[2], data[3]]);
+
+            if length > max_payload_len {
+                if mode != DecodeMode::Lossy {
+                    items.push(Err(ProtocolError::PayloadTooLarge(length as u16)));
+                }
+
+                data = &data[usize::min(HEADER_LEN + length, data.len())..];
+                continue;
+            }
+
+            if data.len() < HEADER_LEN + length {
+                break;
+            }
+
+            match mode.apply(Event::from_data(code, &data[HEADER_LEN..HEADER_LEN + length])) {
+                Ok(Some(event)) => items.push(Ok(event)),
+                Ok(None) => {},
+                Err(err) => items.push(Err(protocol_error(err))),
+            }
+
+            data = &data[HEADER_LEN + length..];
+        }
+
+        items
+    }
+
+    /// Feed the data in chunks directly into an event decoder.
+    pub fn read_decoder(&self, max_payload_len: usize) -> Vec<Item> {
+        let mut decoder = EventDecoder::new()
+            .with_decode_mode(self.mode.into())
+            .with_max_payload_len(max_payload_len);
+
+        let mut reader = self.reader();
+        let mut buf = [0; u8::MAX as usize];
+        let mut items = Vec::new();
+
+        loop {
+            match reader.read_events(&mut buf).unwrap() {
+                0 => return items,
+                n => decoder.feed(&buf[..n]),
+            }
+
+            for result in &mut decoder {
+                items.push(result.map_err(protocol_error));
+            }
+        }
+    }
+
+    pub fn read_blocking(&self) -> Vec<Item> {
+        let mut device = Device::from(self.reader());
+        let mut events = device.events().unwrap().with_decode_mode(self.mode.into());
+
+        self.collect(|| Some(events.read_next_blocking()))
+    }
+
+    pub fn read_stream(&self) -> Vec<Item> {
+        let mut device = Device::from(self.reader());
+        let mut events = device.events_async().unwrap().with_decode_mode(self.mode.into());
+
+        self.collect(|| run(events.next()))
+    }
+
+    pub fn read_async(&self) -> Vec<Item> {
+        let mut device = Device::from(self.reader());
+        let mut events = device.events_async().unwrap().with_decode_mode(self.mode.into());
+
+        self.collect(|| Some(run(events.read_next())))
+    }
+
+    fn reader(&self) -> ChunkedReader {
+        let sizes: Vec<usize> = self.chunks.iter()
+            .chain(std::iter::once(&u8::MAX))
+            .map(|n| *n as usize)
+            .collect();
+
+        ChunkedReader::new(&self.data, &sizes)
+    }
+
+    fn collect<F>(&self, mut next: F) -> Vec<Item>
+    where
+        F: FnMut() -> Option<std::io::Result<Event>>,
+    {
+        // every event takes up at least its header
+        let limit = self.data.len() / std::mem::size_of::<uapi::EventHeader>() + 1;
+        let mut items = Vec::new();
+
+        for _ in 0..=limit {
+            match next() {
+                Some(Ok(event)) => items.push(Ok(event)),
+                Some(Err(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof => return items,
+                Some(Err(err)) => items.push(Err(protocol_error(err))),
+                None => return items,
+            }
+        }
+
+        panic!("reader did not stop at end of data");
+    }
+}
+
+
+fn protocol_error(err: std::io::Error) -> ProtocolError {
+    match err.get_ref().and_then(|e| e.downcast_ref::<ProtocolError>()) {
+        Some(err) => *err,
+        None => panic!("unexpected error: {}", err),
+    }
+}
+
+
+#[derive(Default)]
+struct Flag(AtomicBool);
+
+impl ArcWake for Flag {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Poll the future to completion, failing if it returns `Pending` without
+/// having arranged for a wakeup.
+fn run<F: Future>(future: F) -> F::Output {
+    let flag = Arc::new(Flag::default());
+    let waker = futures::task::waker(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    futures::pin_mut!(future);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => assert!(flag.0.swap(false, Ordering::SeqCst), "pending without wakeup"),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::File;
+
+use async_io::Async;
+
+
+/// Device file registered with the `async-io` reactor.
+///
+/// The file is switched to non-blocking mode and read on readiness. Blocking
+/// reads, e.g. via [`sdtx::Device::events`], therefore fail with
+/// [`WouldBlock`](std::io::ErrorKind::WouldBlock) if no events are available.
+pub struct AsyncFile {
+    file: Async<File>,
+}
+
+impl AsyncFile {
+    pub fn new(file: File) -> std::io::Result<Self> {
+        Ok(AsyncFile { file: Async::new(file)? })
+    }
+
+    pub async fn try_clone(&self) -> std::io::Result<Self> {
+        AsyncFile::new(self.file.get_ref().try_clone()?)
+    }
+
+    pub fn inner(&self) -> &File {
+        self.file.get_ref()
+    }
+
+    /// Mutable access to the file.
+    ///
+    /// Unlike `sdtx_tokio::AsyncFile::inner_mut`, this is unsafe, as required
+    /// by [`Async::get_mut`].
+    ///
+    /// # Safety
+    ///
+    /// The file must not be closed or replaced while it is registered with
+    /// the reactor.
+    pub unsafe fn inner_mut(&mut self) -> &mut File {
+        self.file.get_mut()
+    }
+
+    /// Deregister the file from the reactor and return it.
+    ///
+    /// Unlike `sdtx_tokio::AsyncFile::into_inner`, this reports failure to
+    /// deregister, as done by [`Async::into_inner`]. The file is closed in
+    /// that case.
+    pub fn into_inner(self) -> std::io::Result<File> {
+        self.file.into_inner()
+    }
+}
+
+impl futures::io::AsyncRead for AsyncFile {
+    fn poll_read(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>, buf: &mut [u8])
+            -> std::task::Poll<std::io::Result<usize>>
+    {
+        std::pin::Pin::new(&mut self.file).poll_read(cx, buf)
+    }
+}
+
+/// Forwards all operations to the underlying file.
+impl sdtx::DtxBackend for AsyncFile {
+    fn events_enable(&self) -> std::io::Result<()> {
+        self.inner().events_enable()
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        self.inner().events_disable()
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        self.inner().latch_lock()
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        self.inner().latch_unlock()
+    }
+
+    fn latch_request(&self) -> std::io::Result<()> {
+        self.inner().latch_request()
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        self.inner().latch_confirm()
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        self.inner().latch_heartbeat()
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        self.inner().latch_cancel()
+    }
+
+    fn get_base_info(&self) -> std::io::Result<sdtx::uapi::BaseInfo> {
+        self.inner().get_base_info()
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        self.inner().get_device_mode()
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        self.inner().get_latch_status()
+    }
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::io::Read::read(&mut self.inner(), buf)
+    }
+}
+
+impl std::os::unix::io::AsRawFd for AsyncFile {
+    fn as_raw_fd(&self) -> std::os::unix::prelude::RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+
+pub type Device = sdtx::Device<AsyncFile>;
+
+pub async fn connect() -> std::io::Result<Device> {
+    let file = File::open(sdtx::DEFAULT_DEVICE_FILE_PATH)?;
+    let file = AsyncFile::new(file)?;
+
+    Ok(Device::from(file))
+}
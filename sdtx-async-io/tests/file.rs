@@ -0,0 +1,73 @@
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::net::UnixStream;
+
+use futures::io::AsyncReadExt;
+
+use sdtx::{DtxBackend, Event};
+use sdtx_async_io::AsyncFile;
+
+
+fn socket_file(stream: UnixStream) -> File {
+    File::from(OwnedFd::from(stream))
+}
+
+#[test]
+fn read_when_ready() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    async_io::block_on(async {
+        let mut file = AsyncFile::new(socket_file(rx)).unwrap();
+        let mut buf = [0; 16];
+
+        assert!(futures::poll!(file.read(&mut buf)).is_pending());
+
+        let data = Event::Request.to_bytes();
+        tx.write_all(&data).unwrap();
+
+        let n = file.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], &data[..]);
+
+        assert!(futures::poll!(file.read(&mut buf)).is_pending());
+
+        drop(tx);
+        assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn blocking_read_would_block() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    let mut file = AsyncFile::new(socket_file(rx)).unwrap();
+    let mut buf = [0; 16];
+
+    let err = file.read_events(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+    tx.write_all(&[1, 2, 3]).unwrap();
+    assert_eq!(file.read_events(&mut buf).unwrap(), 3);
+}
+
+#[test]
+fn into_inner() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    let file = AsyncFile::new(socket_file(rx)).unwrap();
+    let mut file = file.into_inner().unwrap();
+
+    tx.write_all(&[1, 2, 3]).unwrap();
+
+    let mut buf = [0; 16];
+    assert_eq!(std::io::Read::read(&mut file, &mut buf).unwrap(), 3);
+}
+
+#[test]
+fn events_async_issues_ioctls() {
+    let (_tx, rx) = UnixStream::pair().unwrap();
+
+    // the stream is available, but enabling events fails on a non-DTX file
+    let mut device = sdtx_async_io::Device::from(AsyncFile::new(socket_file(rx)).unwrap());
+    assert!(matches!(device.events_async(), Err(sdtx::Error::NotSupported { .. })));
+}
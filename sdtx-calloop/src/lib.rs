@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::os::unix::io::{AsFd, AsRawFd, OwnedFd};
+
+use calloop::generic::Generic;
+use calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, TokenFactory};
+
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+
+use sdtx::{DecodeMode, Device, DtxBackend, Event, EventDecoder, ProtocolError};
+
+
+/// Event source for a [`calloop`] event loop, yielding DTX events.
+///
+/// The callback receives each event together with the device, so it can
+/// respond directly, e.g. via [`Device::latch_confirm`] or
+/// [`Device::latch_cancel`]. Events are enabled while the source exists and
+/// the device file is switched to non-blocking mode. The source removes
+/// itself from the event loop once the device reaches end of file.
+///
+/// Events that cannot be decoded, e.g. due to an oversized payload or, in
+/// strict mode, an unknown value, are passed to the callback as error and the
+/// source continues with the next event. Only failing to read from the device
+/// is returned from dispatching.
+#[derive(Debug)]
+pub struct DtxSource<F: DtxBackend + AsFd = File> {
+    device: Device<F>,
+    decoder: EventDecoder,
+    source: Generic<OwnedFd>,
+}
+
+impl<F: DtxBackend + AsFd> DtxSource<F> {
+    pub fn new(device: Device<F>) -> Result<Self, sdtx::Error> {
+        let fd = device.file().as_fd().try_clone_to_owned()?;
+
+        let flags = fcntl(fd.as_raw_fd(), FcntlArg::F_GETFL).map_err(std::io::Error::from)?;
+        let flags = OFlag::from_bits_truncate(flags) | OFlag::O_NONBLOCK;
+        fcntl(fd.as_raw_fd(), FcntlArg::F_SETFL(flags)).map_err(std::io::Error::from)?;
+
+        device.events_enable()?;
+
+        Ok(DtxSource {
+            device,
+            decoder: EventDecoder::new(),
+            source: Generic::new(fd, Interest::READ, Mode::Level),
+        })
+    }
+
+    pub fn device(&self) -> &Device<F> {
+        &self.device
+    }
+
+    pub fn decode_mode(&self) -> DecodeMode {
+        self.decoder.decode_mode()
+    }
+
+    pub fn set_decode_mode(&mut self, mode: DecodeMode) {
+        self.decoder.set_decode_mode(mode);
+    }
+
+    pub fn with_decode_mode(mut self, mode: DecodeMode) -> Self {
+        self.decoder.set_decode_mode(mode);
+        self
+    }
+}
+
+impl<F: DtxBackend + AsFd> Drop for DtxSource<F> {
+    fn drop(&mut self) {
+        let _ = self.device.events_disable();
+    }
+}
+
+impl<F: DtxBackend + AsFd> EventSource for DtxSource<F> {
+    type Event = Result<Event, ProtocolError>;
+    type Metadata = Device<F>;
+    type Ret = ();
+    type Error = std::io::Error;
+
+    fn process_events<C>(&mut self, readiness: Readiness, token: Token, mut callback: C)
+            -> Result<PostAction, std::io::Error>
+    where
+        C: FnMut(Result<Event, ProtocolError>, &mut Device<F>),
+    {
+        let DtxSource { device, decoder, source } = self;
+
+        source.process_events(readiness, token, |_, _| {
+            let mut buf = [0; 128];
+
+            loop {
+                for result in &mut *decoder {
+                    let result = match result {
+                        Ok(event) => Ok(event),
+                        Err(err) => Err(protocol_error(err)?),
+                    };
+
+                    callback(result, device);
+                }
+
+                match device.file_mut().read_events(&mut buf) {
+                    Ok(0) => return Ok(PostAction::Remove),
+                    Ok(n) => decoder.feed(&buf[..n]),
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return Ok(PostAction::Continue),
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                }
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.register(poll, token_factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, token_factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.source.reregister(poll, token_factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.source.unregister(poll)
+    }
+}
+
+
+/// Extract the protocol error from a decoding error, passing on other errors.
+fn protocol_error(err: std::io::Error) -> std::io::Result<ProtocolError> {
+    if let Some(&err) = err.get_ref().and_then(|e| e.downcast_ref::<ProtocolError>()) {
+        return Ok(err);
+    }
+
+    Err(err)
+}
+
+
+pub fn connect() -> Result<DtxSource, sdtx::Error> {
+    DtxSource::new(Device::open()?)
+}
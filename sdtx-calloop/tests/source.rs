@@ -0,0 +1,144 @@
+use std::io::{Read, Write};
+use std::os::unix::io::{AsFd, BorrowedFd};
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use calloop::EventLoop;
+
+use sdtx::event::DeviceMode;
+use sdtx::{uapi, Device, DtxBackend, Event, ProtocolError};
+use sdtx_calloop::DtxSource;
+
+
+/// Backend reading events from a socket, counting latch confirmations.
+#[derive(Debug)]
+struct Socket {
+    stream: UnixStream,
+    confirmed: Arc<AtomicUsize>,
+}
+
+impl AsFd for Socket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+
+impl DtxBackend for Socket {
+    fn events_enable(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_request(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        self.confirmed.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn get_base_info(&self) -> std::io::Result<uapi::BaseInfo> {
+        Ok(uapi::BaseInfo { state: 0, base_id: 0 })
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        Ok(0)
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        Ok(0)
+    }
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+#[test]
+fn dispatch_events() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    let confirmed = Arc::new(AtomicUsize::new(0));
+
+    let device = Device::from(Socket { stream: rx, confirmed: confirmed.clone() });
+    let source = DtxSource::new(device).unwrap();
+
+    let mut event_loop: EventLoop<Vec<Event>> = EventLoop::try_new().unwrap();
+    event_loop.handle()
+        .insert_source(source, |event, device, received| {
+            let event = event.unwrap();
+
+            if event == Event::Request {
+                device.latch_confirm().unwrap();
+            }
+
+            received.push(event);
+        })
+        .unwrap();
+
+    let events = [Event::Request, Event::DeviceMode { mode: DeviceMode::Tablet }];
+    let data: Vec<u8> = events.iter().flat_map(Event::to_bytes).collect();
+
+    // split an event across writes to check that partial events are kept
+    tx.write_all(&data[..6]).unwrap();
+
+    let mut received = Vec::new();
+    event_loop.dispatch(Some(Duration::from_secs(5)), &mut received).unwrap();
+    assert_eq!(received, events[..1]);
+    assert_eq!(confirmed.load(Ordering::SeqCst), 1);
+
+    tx.write_all(&data[6..]).unwrap();
+
+    event_loop.dispatch(Some(Duration::from_secs(5)), &mut received).unwrap();
+    assert_eq!(received, events);
+}
+
+#[test]
+fn continue_after_decode_error() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    let device = Device::from(Socket { stream: rx, confirmed: Arc::default() });
+    let source = DtxSource::new(device).unwrap();
+
+    let mut event_loop: EventLoop<Vec<Result<Event, ProtocolError>>> = EventLoop::try_new().unwrap();
+    event_loop.handle()
+        .insert_source(source, |event, _, received| received.push(event))
+        .unwrap();
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&2000u16.to_ne_bytes());
+    data.extend_from_slice(&uapi::SDTX_EVENT_REQUEST.to_ne_bytes());
+    data.extend_from_slice(&[0; 2000]);
+    data.extend_from_slice(&Event::Request.to_bytes());
+
+    tx.write_all(&data).unwrap();
+
+    let mut received = Vec::new();
+    while received.len() < 2 {
+        event_loop.dispatch(Some(Duration::from_secs(5)), &mut received).unwrap();
+    }
+
+    assert_eq!(received, [Err(ProtocolError::PayloadTooLarge(2000)), Ok(Event::Request)]);
+}
@@ -0,0 +1,174 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use serde_json::json;
+
+use sdtx::capture::Recorder;
+use sdtx::{Device, DtxBackend, Error, LatchStatus};
+
+
+const EXIT_FAILURE: u8 = 1;
+const EXIT_PROTOCOL_ERROR: u8 = 3;
+const EXIT_HARDWARE_ERROR: u8 = 4;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  Failure, e.g. I/O error or failed communication with the EC
+  2  Invalid usage
+  3  Invalid data received from the kernel
+  4  The latch reports a hardware error (status only)";
+
+
+#[derive(Parser)]
+#[command(name = "sdtx-cli", version, about = "Query and control the Surface DTX device")]
+#[command(after_help = EXIT_CODES)]
+struct Cli {
+    /// Path of the DTX device file
+    #[arg(short, long, default_value = sdtx::DEFAULT_DEVICE_FILE_PATH)]
+    device: PathBuf,
+
+    /// Print output as JSON
+    #[arg(short, long)]
+    json: bool,
+
+    /// Record all device interaction to a capture file
+    #[arg(short, long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show base info, device mode, and latch status
+    Status,
+
+    /// Print events as they are received
+    Monitor,
+
+    /// Lock the latch, preventing it from opening on timeout
+    Lock,
+
+    /// Unlock the latch
+    Unlock,
+
+    /// Request detachment, or abort the detachment in progress
+    Request,
+
+    /// Confirm the detachment in progress, opening the latch
+    Confirm,
+
+    /// Keep the detachment in progress alive
+    Heartbeat,
+
+    /// Cancel the detachment in progress
+    Cancel,
+}
+
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(&cli) {
+        Ok(code) => code,
+        Err(err) => {
+            let mut msg = err.to_string();
+            let mut source = std::error::Error::source(&err);
+
+            while let Some(err) = source {
+                msg += &format!(": {err}");
+                source = err.source();
+            }
+
+            eprintln!("sdtx-cli: {msg}");
+
+            match err {
+                Error::ProtocolError { .. } => ExitCode::from(EXIT_PROTOCOL_ERROR),
+                _ => ExitCode::from(EXIT_FAILURE),
+            }
+        },
+    }
+}
+
+fn run(cli: &Cli) -> Result<ExitCode, Error> {
+    let mut device = Device::open_path(&cli.device)?;
+
+    match &cli.record {
+        Some(path) => {
+            let capture = std::fs::File::create(path)?;
+            let recorder = Recorder::new(device.into_file(), capture)?;
+
+            execute(cli, &mut Device::from(recorder))
+        },
+        None => execute(cli, &mut device),
+    }
+}
+
+fn execute<F: DtxBackend>(cli: &Cli, device: &mut Device<F>) -> Result<ExitCode, Error> {
+    match cli.command {
+        Command::Status    => status(device, cli.json),
+        Command::Monitor   => monitor(device, cli.json),
+        Command::Lock      => command(device, Device::latch_lock),
+        Command::Unlock    => command(device, Device::latch_unlock),
+        Command::Request   => command(device, Device::latch_request),
+        Command::Confirm   => command(device, Device::latch_confirm),
+        Command::Heartbeat => command(device, Device::latch_heartbeat),
+        Command::Cancel    => command(device, Device::latch_cancel),
+    }
+}
+
+fn command<F, C>(device: &Device<F>, cmd: C) -> Result<ExitCode, Error>
+where
+    F: DtxBackend,
+    C: FnOnce(&Device<F>) -> Result<(), Error>,
+{
+    cmd(device)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn status<F: DtxBackend>(device: &Device<F>, json: bool) -> Result<ExitCode, Error> {
+    let base = device.get_base_info()?;
+    let mode = device.get_device_mode()?;
+    let latch = device.get_latch_status()?;
+
+    if json {
+        let status = json!({
+            "base": base,
+            "device_mode": mode,
+            "latch_status": latch,
+        });
+
+        println!("{status}");
+    } else {
+        println!("Base:         {} (type: {}, id: {:#04x})", base.state, base.device_type, base.id);
+        println!("Device mode:  {mode}");
+        println!("Latch status: {latch}");
+    }
+
+    match latch {
+        LatchStatus::Error(_) => Ok(ExitCode::from(EXIT_HARDWARE_ERROR)),
+        _ => Ok(ExitCode::SUCCESS),
+    }
+}
+
+fn monitor<F: DtxBackend>(device: &mut Device<F>, json: bool) -> Result<ExitCode, Error> {
+    let stdout = std::io::stdout();
+
+    for event in device.events()? {
+        let event = event?;
+
+        let mut out = stdout.lock();
+        if json {
+            writeln!(out, "{}", json!(event))?;
+        } else {
+            writeln!(out, "{event}")?;
+        }
+        out.flush()?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,213 @@
+use std::convert::TryInto;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+
+pub const CUSE_DEVICE_PATH: &str = "/dev/cuse";
+
+const FUSE_KERNEL_VERSION: u32 = 7;
+const FUSE_KERNEL_MINOR_VERSION: u32 = 31;
+
+const FUSE_MIN_READ_BUFFER: usize = 8192;
+const MAX_READ: u32 = 4096;
+const MAX_WRITE: u32 = 4096;
+
+const FUSE_OPEN: u32 = 14;
+const FUSE_READ: u32 = 15;
+const FUSE_RELEASE: u32 = 18;
+const FUSE_FLUSH: u32 = 25;
+const FUSE_INTERRUPT: u32 = 36;
+const FUSE_DESTROY: u32 = 38;
+const FUSE_IOCTL: u32 = 39;
+const FUSE_POLL: u32 = 40;
+const CUSE_INIT: u32 = 4096;
+
+const FUSE_NOTIFY_POLL: i32 = 1;
+
+const FOPEN_DIRECT_IO: u32 = 1 << 0;
+const FOPEN_NONSEEKABLE: u32 = 1 << 2;
+
+pub const FUSE_POLL_SCHEDULE_NOTIFY: u32 = 1 << 0;
+
+const IN_HEADER_LEN: usize = 40;
+const OUT_HEADER_LEN: usize = 16;
+
+
+#[derive(Debug)]
+pub enum Request {
+    Init { major: u32, minor: u32 },
+    Open,
+    Read { fh: u64, size: u32, flags: u32 },
+    Release { fh: u64 },
+    Flush,
+    Interrupt { unique: u64 },
+    Ioctl { fh: u64, cmd: u32, out_size: u32 },
+    Poll { fh: u64, kh: u64, flags: u32 },
+    Destroy,
+    Unsupported { opcode: u32 },
+}
+
+
+pub struct Channel {
+    file: File,
+    buffer: Vec<u8>,
+}
+
+impl Channel {
+    pub fn open() -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(CUSE_DEVICE_PATH)?;
+
+        let buffer = vec![0; FUSE_MIN_READ_BUFFER + MAX_WRITE as usize];
+
+        Ok(Channel { file, buffer })
+    }
+
+    pub fn receive(&mut self) -> std::io::Result<(u64, Request)> {
+        let len = loop {
+            match self.file.read(&mut self.buffer) {
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+
+        let buf = &self.buffer[..len];
+        if buf.len() < IN_HEADER_LEN {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+
+        let opcode = u32_at(buf, 4);
+        let unique = u64_at(buf, 8);
+        let arg = &buf[IN_HEADER_LEN..];
+
+        let request = match opcode {
+            CUSE_INIT => Request::Init {
+                major: u32_at(arg, 0),
+                minor: u32_at(arg, 4),
+            },
+            FUSE_OPEN => Request::Open,
+            FUSE_READ => Request::Read {
+                fh: u64_at(arg, 0),
+                size: u32_at(arg, 16),
+                flags: u32_at(arg, 32),
+            },
+            FUSE_RELEASE => Request::Release {
+                fh: u64_at(arg, 0),
+            },
+            FUSE_FLUSH => Request::Flush,
+            FUSE_INTERRUPT => Request::Interrupt {
+                unique: u64_at(arg, 0),
+            },
+            FUSE_IOCTL => Request::Ioctl {
+                fh: u64_at(arg, 0),
+                cmd: u32_at(arg, 12),
+                out_size: u32_at(arg, 28),
+            },
+            FUSE_POLL => Request::Poll {
+                fh: u64_at(arg, 0),
+                kh: u64_at(arg, 8),
+                flags: u32_at(arg, 16),
+            },
+            FUSE_DESTROY => Request::Destroy,
+            opcode => Request::Unsupported { opcode },
+        };
+
+        Ok((unique, request))
+    }
+
+    pub fn reply_init(&self, unique: u64, devname: &str) -> std::io::Result<()> {
+        let mut data = Vec::new();
+
+        for value in &[FUSE_KERNEL_VERSION, FUSE_KERNEL_MINOR_VERSION, 0, 0, MAX_READ, MAX_WRITE, 0, 0] {
+            data.extend_from_slice(&value.to_ne_bytes());
+        }
+        data.extend_from_slice(&[0; 10 * 4]);
+
+        data.extend_from_slice(format!("DEVNAME={devname}").as_bytes());
+        data.push(0);
+
+        self.reply(unique, &data)
+    }
+
+    pub fn reply_open(&self, unique: u64, fh: u64) -> std::io::Result<()> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&fh.to_ne_bytes());
+        data.extend_from_slice(&(FOPEN_DIRECT_IO | FOPEN_NONSEEKABLE).to_ne_bytes());
+        data.extend_from_slice(&0u32.to_ne_bytes());
+
+        self.reply(unique, &data)
+    }
+
+    pub fn reply_ioctl(&self, unique: u64, out: &[u8]) -> std::io::Result<()> {
+        let mut data = vec![0; 16];
+        data.extend_from_slice(out);
+
+        self.reply(unique, &data)
+    }
+
+    pub fn reply_poll(&self, unique: u64, revents: u32) -> std::io::Result<()> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&revents.to_ne_bytes());
+        data.extend_from_slice(&0u32.to_ne_bytes());
+
+        self.reply(unique, &data)
+    }
+
+    pub fn reply(&self, unique: u64, data: &[u8]) -> std::io::Result<()> {
+        self.send(0, unique, data)
+    }
+
+    pub fn reply_err(&self, unique: u64, errno: i32) -> std::io::Result<()> {
+        self.send(-errno, unique, &[])
+    }
+
+    pub fn notify_poll(&self, kh: u64) -> std::io::Result<()> {
+        self.send(FUSE_NOTIFY_POLL, 0, &kh.to_ne_bytes())
+    }
+
+    fn send(&self, error: i32, unique: u64, data: &[u8]) -> std::io::Result<()> {
+        let mut msg = Vec::with_capacity(OUT_HEADER_LEN + data.len());
+        msg.extend_from_slice(&((OUT_HEADER_LEN + data.len()) as u32).to_ne_bytes());
+        msg.extend_from_slice(&error.to_ne_bytes());
+        msg.extend_from_slice(&unique.to_ne_bytes());
+        msg.extend_from_slice(data);
+
+        match (&self.file).write(&msg) {
+            // the request has been interrupted and is gone already
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Ok(()),
+        }
+    }
+}
+
+impl AsRawFd for Channel {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+
+pub fn check_version(major: u32, minor: u32) -> std::io::Result<()> {
+    if major != FUSE_KERNEL_VERSION || minor < 11 {
+        let msg = format!("unsupported CUSE protocol version {major}.{minor}");
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, msg));
+    }
+
+    Ok(())
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    buf.get(offset..offset + 4)
+        .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+        .unwrap_or(0)
+}
+
+fn u64_at(buf: &[u8], offset: usize) -> u64 {
+    buf.get(offset..offset + 8)
+        .map(|b| u64::from_ne_bytes(b.try_into().unwrap()))
+        .unwrap_or(0)
+}
@@ -0,0 +1,428 @@
+use std::collections::{HashMap, VecDeque};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+use futures::io::AsyncRead;
+
+use sdtx::sim::{SimDevice, Simulator};
+use sdtx::{DeviceMode, DeviceType, DtxBackend, HardwareError};
+
+mod cuse;
+use cuse::{Channel, Request};
+
+
+const DEFAULT_DEVICE_NAME: &str = "surface/dtx-fake";
+const TICK: Duration = Duration::from_millis(100);
+
+const USAGE: &str = "\
+Usage: sdtx-fakedev [--name <devname>] [--manual-time]
+
+Expose a simulated Surface DTX device as /dev/<devname> via CUSE.
+
+Options:
+    --name <devname>    Device name below /dev [default: surface/dtx-fake]
+    --manual-time       Only advance simulated time via the 'advance' command
+
+Commands (one per line on stdin):
+    press                       Press the detach button
+    remove-base                 Physically remove the base
+    attach-base <type> <id>     Attach a base (type: hid, ssh, or number)
+    low-battery <on|off>        Toggle the low clipboard battery condition
+    mode <tablet|laptop|studio> Change the device mode
+    fail <open|remain-open|close>
+                                Let the next latch operation fail
+    timeout <ms>                Set the detach timeout
+    advance <ms>                Advance simulated time
+    shutdown                    Simulate device removal
+    status                      Print the simulator state";
+
+
+struct Options {
+    name: String,
+    realtime: bool,
+}
+
+impl Options {
+    fn parse() -> Result<Self, String> {
+        let mut opts = Options { name: DEFAULT_DEVICE_NAME.into(), realtime: true };
+        let mut args = std::env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--name" => opts.name = args.next().ok_or("missing value for '--name'")?,
+                "--manual-time" => opts.realtime = false,
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                },
+                arg => return Err(format!("unexpected argument '{arg}'")),
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+
+struct PipeWaker {
+    fd: RawFd,
+}
+
+impl Wake for PipeWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let _ = unsafe { libc::write(self.fd, [1u8].as_ptr() as *const libc::c_void, 1) };
+    }
+}
+
+
+struct OpenFile {
+    device: SimDevice,
+    buffer: VecDeque<u8>,
+    reads: VecDeque<(u64, usize)>,
+    poll: Option<u64>,
+    error: Option<i32>,
+}
+
+impl OpenFile {
+    fn is_readable(&self) -> bool {
+        !self.buffer.is_empty() || self.error.is_some()
+    }
+}
+
+
+struct FakeDevice {
+    name: String,
+    channel: Channel,
+    sim: Simulator,
+    files: HashMap<u64, OpenFile>,
+    next_fh: u64,
+    waker: Waker,
+}
+
+impl FakeDevice {
+    fn handle_request(&mut self) -> std::io::Result<bool> {
+        let (unique, request) = self.channel.receive()?;
+
+        match request {
+            Request::Init { major, minor } => {
+                cuse::check_version(major, minor)?;
+                self.channel.reply_init(unique, &self.name)?;
+
+                eprintln!("sdtx-fakedev: serving /dev/{}", self.name);
+            },
+            Request::Open => {
+                let fh = self.next_fh;
+                self.next_fh += 1;
+
+                let file = OpenFile {
+                    device: self.sim.open(),
+                    buffer: VecDeque::new(),
+                    reads: VecDeque::new(),
+                    poll: None,
+                    error: None,
+                };
+
+                self.files.insert(fh, file);
+                self.channel.reply_open(unique, fh)?;
+            },
+            Request::Read { fh, size, flags } => {
+                self.pump()?;
+
+                match self.files.get_mut(&fh) {
+                    Some(file) if !file.is_readable() && flags & libc::O_NONBLOCK as u32 != 0 => {
+                        self.channel.reply_err(unique, libc::EAGAIN)?;
+                    },
+                    Some(file) => file.reads.push_back((unique, size as usize)),
+                    None => self.channel.reply_err(unique, libc::EBADF)?,
+                }
+            },
+            Request::Release { fh } => {
+                self.files.remove(&fh);
+                self.channel.reply(unique, &[])?;
+            },
+            Request::Flush => {
+                self.channel.reply(unique, &[])?;
+            },
+            Request::Interrupt { unique: target } => {
+                for file in self.files.values_mut() {
+                    if let Some(pos) = file.reads.iter().position(|(u, _)| *u == target) {
+                        file.reads.remove(pos);
+                        self.channel.reply_err(target, libc::EINTR)?;
+                    }
+                }
+            },
+            Request::Ioctl { fh, cmd, out_size } => {
+                let result = match self.files.get(&fh) {
+                    Some(file) => ioctl(&file.device, cmd),
+                    None => Err(std::io::Error::from_raw_os_error(libc::EBADF)),
+                };
+
+                match result {
+                    Ok(mut data) => {
+                        data.truncate(out_size as usize);
+                        self.channel.reply_ioctl(unique, &data)?;
+                    },
+                    Err(e) => {
+                        self.channel.reply_err(unique, e.raw_os_error().unwrap_or(libc::EIO))?;
+                    },
+                }
+            },
+            Request::Poll { fh, kh, flags } => {
+                self.pump()?;
+
+                let file = match self.files.get_mut(&fh) {
+                    Some(file) => file,
+                    None => return self.channel.reply_err(unique, libc::EBADF).map(|_| true),
+                };
+
+                let mut revents = 0;
+                if !file.buffer.is_empty() {
+                    revents |= (libc::POLLIN | libc::POLLRDNORM) as u32;
+                }
+                if file.error.is_some() {
+                    revents |= (libc::POLLHUP | libc::POLLERR) as u32;
+                }
+
+                if flags & cuse::FUSE_POLL_SCHEDULE_NOTIFY != 0 {
+                    file.poll = Some(kh);
+                }
+
+                self.channel.reply_poll(unique, revents)?;
+            },
+            Request::Destroy => {
+                return Ok(false);
+            },
+            Request::Unsupported { opcode } => {
+                eprintln!("sdtx-fakedev: unsupported request (opcode {opcode})");
+                self.channel.reply_err(unique, libc::ENOSYS)?;
+            },
+        }
+
+        Ok(true)
+    }
+
+    fn pump(&mut self) -> std::io::Result<()> {
+        let mut cx = Context::from_waker(&self.waker);
+        let mut buf = [0; 128];
+
+        for file in self.files.values_mut() {
+            while file.error.is_none() {
+                match Pin::new(&mut file.device).poll_read(&mut cx, &mut buf) {
+                    Poll::Ready(Ok(0)) | Poll::Pending => break,
+                    Poll::Ready(Ok(n)) => file.buffer.extend(&buf[..n]),
+                    Poll::Ready(Err(e)) => file.error = Some(e.raw_os_error().unwrap_or(libc::EIO)),
+                }
+            }
+
+            while let Some(&(unique, size)) = file.reads.front() {
+                if !file.buffer.is_empty() {
+                    let n = usize::min(size, file.buffer.len());
+                    let data: Vec<u8> = file.buffer.drain(..n).collect();
+
+                    self.channel.reply(unique, &data)?;
+                } else if let Some(err) = file.error {
+                    self.channel.reply_err(unique, err)?;
+                } else {
+                    break;
+                }
+
+                file.reads.pop_front();
+            }
+
+            if file.is_readable() {
+                if let Some(kh) = file.poll.take() {
+                    self.channel.notify_poll(kh)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+fn ioctl(device: &SimDevice, cmd: u32) -> std::io::Result<Vec<u8>> {
+    if (cmd >> 8) & 0xff != 0xa5 {
+        return Err(std::io::Error::from_raw_os_error(libc::ENOTTY));
+    }
+
+    match cmd & 0xff {
+        0x21 => device.events_enable().map(|_| Vec::new()),
+        0x22 => device.events_disable().map(|_| Vec::new()),
+        0x23 => device.latch_lock().map(|_| Vec::new()),
+        0x24 => device.latch_unlock().map(|_| Vec::new()),
+        0x25 => device.latch_request().map(|_| Vec::new()),
+        0x26 => device.latch_confirm().map(|_| Vec::new()),
+        0x27 => device.latch_heartbeat().map(|_| Vec::new()),
+        0x28 => device.latch_cancel().map(|_| Vec::new()),
+        0x29 => device.get_base_info().map(|info| {
+            let (state, base_id) = (info.state, info.base_id);
+            [state.to_ne_bytes(), base_id.to_ne_bytes()].concat()
+        }),
+        0x2a => device.get_device_mode().map(|mode| mode.to_ne_bytes().to_vec()),
+        0x2b => device.get_latch_status().map(|status| status.to_ne_bytes().to_vec()),
+        _ => Err(std::io::Error::from_raw_os_error(libc::EINVAL)),
+    }
+}
+
+fn command(sim: &Simulator, line: &str) -> Result<(), String> {
+    let args: Vec<&str> = line.split_whitespace().collect();
+
+    let millis = |arg: Option<&&str>| -> Result<Duration, String> {
+        let arg = arg.ok_or("missing time argument")?;
+        arg.parse().map(Duration::from_millis).map_err(|_| format!("invalid time '{arg}'"))
+    };
+
+    match args.as_slice() {
+        [] => {},
+        ["press"] => sim.press_detach_button(),
+        ["remove-base"] => sim.remove_base(),
+        ["attach-base", ty, id] => {
+            let ty = match *ty {
+                "hid" => DeviceType::Hid,
+                "ssh" => DeviceType::Ssh,
+                ty => match ty.parse() {
+                    Ok(ty) if ty <= 0x0f => DeviceType::Unknown(ty),
+                    _ => return Err(format!("invalid base type '{ty}'")),
+                },
+            };
+            let id = id.parse().map_err(|_| format!("invalid base id '{id}'"))?;
+
+            sim.attach_base(ty, id);
+        },
+        ["low-battery", "on"] => sim.set_low_battery(true),
+        ["low-battery", "off"] => sim.set_low_battery(false),
+        ["mode", "tablet"] => sim.set_device_mode(DeviceMode::Tablet),
+        ["mode", "laptop"] => sim.set_device_mode(DeviceMode::Laptop),
+        ["mode", "studio"] => sim.set_device_mode(DeviceMode::Studio),
+        ["fail", "open"] => sim.fail_latch(HardwareError::FailedToOpen),
+        ["fail", "remain-open"] => sim.fail_latch(HardwareError::FailedToRemainOpen),
+        ["fail", "close"] => sim.fail_latch(HardwareError::FailedToClose),
+        ["timeout", ..] => sim.set_timeout(millis(args.get(1))?),
+        ["advance", ..] => sim.advance(millis(args.get(1))?),
+        ["shutdown"] => sim.shutdown(),
+        ["status"] => {
+            let base = sim.base_info();
+
+            println!("base:    {} ({}, id: {:#04x})", base.state, base.device_type, base.id);
+            println!("mode:    {}", sim.device_mode());
+            println!("latch:   {}", sim.latch_status());
+            println!("locked:  {}", sim.is_locked());
+            println!("pending: {}", sim.is_detach_in_progress());
+        },
+        _ => return Err(format!("invalid command '{line}'")),
+    }
+
+    Ok(())
+}
+
+fn pipe() -> std::io::Result<(RawFd, RawFd)> {
+    let mut fds = [0; 2];
+
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok((fds[0], fds[1]))
+}
+
+fn read_fd(fd: RawFd, buf: &mut [u8]) -> std::io::Result<usize> {
+    let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+
+    if n < 0 {
+        Err(std::io::Error::last_os_error())
+    } else {
+        Ok(n as usize)
+    }
+}
+
+fn run(opts: Options) -> std::io::Result<()> {
+    let (wake_rx, wake_tx) = pipe()?;
+
+    let mut dev = FakeDevice {
+        name: opts.name,
+        channel: Channel::open()?,
+        sim: Simulator::new(),
+        files: HashMap::new(),
+        next_fh: 0,
+        waker: Waker::from(Arc::new(PipeWaker { fd: wake_tx })),
+    };
+
+    let mut fds = [
+        libc::pollfd { fd: dev.channel.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+        libc::pollfd { fd: wake_rx, events: libc::POLLIN, revents: 0 },
+        libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 },
+    ];
+
+    let timeout = if opts.realtime { TICK.as_millis() as i32 } else { -1 };
+    let mut last = Instant::now();
+    let mut input = Vec::new();
+    let mut buf = [0; 256];
+
+    loop {
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) } < 0 {
+            let err = std::io::Error::last_os_error();
+
+            match err.kind() {
+                std::io::ErrorKind::Interrupted => continue,
+                _ => return Err(err),
+            }
+        }
+
+        if opts.realtime {
+            let now = Instant::now();
+            dev.sim.advance(now - last);
+            last = now;
+        }
+
+        if fds[0].revents != 0 && !dev.handle_request()? {
+            return Ok(());
+        }
+
+        if fds[1].revents != 0 {
+            while read_fd(wake_rx, &mut buf).map(|n| n > 0).unwrap_or(false) {}
+        }
+
+        if fds[2].revents != 0 {
+            let n = read_fd(libc::STDIN_FILENO, &mut buf)?;
+            if n == 0 {
+                fds[2].fd = -1;
+            }
+
+            input.extend_from_slice(&buf[..n]);
+
+            while let Some(pos) = input.iter().position(|c| *c == b'\n') {
+                let line: Vec<u8> = input.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line);
+
+                if let Err(e) = command(&dev.sim, line.trim()) {
+                    eprintln!("sdtx-fakedev: {e}");
+                }
+            }
+        }
+
+        dev.pump()?;
+    }
+}
+
+fn main() {
+    let opts = match Options::parse() {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("sdtx-fakedev: {e}\n\n{USAGE}");
+            std::process::exit(2);
+        },
+    };
+
+    if let Err(e) = run(opts) {
+        eprintln!("sdtx-fakedev: {e}");
+        std::process::exit(1);
+    }
+}
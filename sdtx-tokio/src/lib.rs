@@ -1,48 +1,138 @@
-use tokio::fs::File;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::Read;
+use std::os::unix::io::AsRawFd;
 
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
 
+use tokio::io::unix::AsyncFd;
+
+
+/// Device file registered with the tokio reactor.
+///
+/// The file is switched to non-blocking mode and read on readiness, without
+/// occupying a thread of the blocking pool. Consequently, blocking reads, e.g.
+/// via [`sdtx::Device::events`], fail with
+/// [`WouldBlock`](std::io::ErrorKind::WouldBlock) instead of waiting for
+/// events. They can only be used to drain available events via
+/// [`sdtx::EventStream::try_read_events`].
 pub struct AsyncFile {
-    file: File,
+    file: AsyncFd<File>,
 }
 
 impl AsyncFile {
-    pub fn new(file: File) -> Self {
-        AsyncFile { file }
+    /// Register the file with the reactor of the current tokio runtime.
+    ///
+    /// Fails if called outside of a tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the I/O driver of the current runtime is not enabled.
+    pub fn new(file: File) -> std::io::Result<Self> {
+        tokio::runtime::Handle::try_current()
+            .map_err(std::io::Error::other)?;
+
+        let flags = fcntl(file.as_raw_fd(), FcntlArg::F_GETFL)?;
+        let flags = OFlag::from_bits_truncate(flags) | OFlag::O_NONBLOCK;
+        fcntl(file.as_raw_fd(), FcntlArg::F_SETFL(flags))?;
+
+        Ok(AsyncFile { file: AsyncFd::new(file)? })
     }
 
     pub async fn try_clone(&self) -> std::io::Result<Self> {
-        Ok(AsyncFile { file: self.file.try_clone().await? })
+        AsyncFile::new(self.file.get_ref().try_clone()?)
     }
 
     pub fn inner(&self) -> &File {
-        &self.file
+        self.file.get_ref()
     }
 
     pub fn inner_mut(&mut self) -> &mut File {
-        &mut self.file
+        self.file.get_mut()
     }
 
     pub fn into_inner(self) -> File {
-        self.file
+        self.file.into_inner()
     }
 }
 
-impl From<File> for AsyncFile {
-    fn from(file: File) -> Self {
-        Self::new(file)
+/// Convert an open tokio file, failing if an operation on it is still in
+/// flight.
+impl TryFrom<tokio::fs::File> for AsyncFile {
+    type Error = std::io::Error;
+
+    fn try_from(file: tokio::fs::File) -> std::io::Result<Self> {
+        let file = file.try_into_std().map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::WouldBlock, "file has an operation in flight")
+        })?;
+
+        AsyncFile::new(file)
     }
 }
 
 impl futures::io::AsyncRead for AsyncFile {
-    fn poll_read(mut self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>, buf: &mut [u8])
+    fn poll_read(self: std::pin::Pin<&mut Self>, cx: &mut std::task::Context<'_>, buf: &mut [u8])
             -> std::task::Poll<std::io::Result<usize>>
     {
-        let mut rb = tokio::io::ReadBuf::new(buf);
-        let pin = std::pin::Pin::new(&mut self.file);
+        loop {
+            let mut guard = futures::ready!(self.file.poll_read_ready(cx))?;
+
+            match guard.try_io(|file| file.get_ref().read(buf)) {
+                Ok(result) => return std::task::Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/// Forwards all operations to the underlying file.
+impl sdtx::DtxBackend for AsyncFile {
+    fn events_enable(&self) -> std::io::Result<()> {
+        self.inner().events_enable()
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        self.inner().events_disable()
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        self.inner().latch_lock()
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        self.inner().latch_unlock()
+    }
 
-        futures::ready!(tokio::io::AsyncRead::poll_read(pin, cx, &mut rb))?;
+    fn latch_request(&self) -> std::io::Result<()> {
+        self.inner().latch_request()
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        self.inner().latch_confirm()
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        self.inner().latch_heartbeat()
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        self.inner().latch_cancel()
+    }
+
+    fn get_base_info(&self) -> std::io::Result<sdtx::uapi::BaseInfo> {
+        self.inner().get_base_info()
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        self.inner().get_device_mode()
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        self.inner().get_latch_status()
+    }
 
-        std::task::Poll::Ready(Ok(rb.filled().len()))
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner_mut().read_events(buf)
     }
 }
 
@@ -57,7 +147,7 @@ pub type Device = sdtx::Device<AsyncFile>;
 
 pub async fn connect() -> std::io::Result<Device> {
     let file = tokio::fs::File::open(sdtx::DEFAULT_DEVICE_FILE_PATH).await?;
-    let file = AsyncFile::from(file);
+    let file = AsyncFile::new(file.into_std().await)?;
 
     Ok(Device::from(file))
 }
@@ -0,0 +1,88 @@
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::net::UnixStream;
+
+use futures::io::AsyncReadExt;
+
+use tokio::runtime::Runtime;
+
+use sdtx::Event;
+use sdtx_tokio::AsyncFile;
+
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap()
+}
+
+fn socket_file(stream: UnixStream) -> File {
+    File::from(OwnedFd::from(stream))
+}
+
+#[test]
+fn read_when_ready() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    runtime().block_on(async {
+        let mut file = AsyncFile::new(socket_file(rx)).unwrap();
+        let mut buf = [0; 16];
+
+        assert!(futures::poll!(file.read(&mut buf)).is_pending());
+
+        let data = Event::Request.to_bytes();
+        tx.write_all(&data).unwrap();
+
+        let n = file.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], &data[..]);
+
+        assert!(futures::poll!(file.read(&mut buf)).is_pending());
+
+        drop(tx);
+        assert_eq!(file.read(&mut buf).await.unwrap(), 0);
+    });
+}
+
+#[test]
+fn from_tokio_file() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    runtime().block_on(async {
+        let file = tokio::fs::File::from_std(socket_file(rx));
+        let mut file = AsyncFile::try_from(file).unwrap();
+
+        tx.write_all(&[1, 2, 3]).unwrap();
+
+        let mut buf = [0; 16];
+        let n = file.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..n], [1, 2, 3]);
+    });
+}
+
+#[test]
+fn new_outside_runtime() {
+    let (_tx, rx) = UnixStream::pair().unwrap();
+
+    assert!(AsyncFile::new(socket_file(rx)).is_err());
+}
+
+#[test]
+fn blocking_read_would_block() {
+    use sdtx::DtxBackend;
+
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+
+    runtime().block_on(async {
+        let mut file = AsyncFile::new(socket_file(rx)).unwrap();
+        let mut buf = [0; 16];
+
+        let err = file.read_events(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+
+        tx.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(file.read_events(&mut buf).unwrap(), 3);
+    });
+}
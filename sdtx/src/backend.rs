@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+
+use crate::uapi;
+
+
+/// Raw access to the DTX ioctls and event data, as used by [`Device`](crate::Device).
+///
+/// Implemented for [`File`] by issuing the actual ioctls on its file
+/// descriptor. Wrappers around a file implement it explicitly, usually by
+/// forwarding to the file, so they can adapt individual operations. Values
+/// are returned undecoded, as seen on the wire.
+pub trait DtxBackend {
+    fn events_enable(&self) -> std::io::Result<()>;
+    fn events_disable(&self) -> std::io::Result<()>;
+
+    fn latch_lock(&self) -> std::io::Result<()>;
+    fn latch_unlock(&self) -> std::io::Result<()>;
+
+    fn latch_request(&self) -> std::io::Result<()>;
+    fn latch_confirm(&self) -> std::io::Result<()>;
+    fn latch_heartbeat(&self) -> std::io::Result<()>;
+    fn latch_cancel(&self) -> std::io::Result<()>;
+
+    fn get_base_info(&self) -> std::io::Result<uapi::BaseInfo>;
+    fn get_device_mode(&self) -> std::io::Result<u16>;
+    fn get_latch_status(&self) -> std::io::Result<u16>;
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize>;
+}
+
+impl DtxBackend for File {
+    fn events_enable(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_events_enable(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_events_disable(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_latch_lock(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_latch_unlock(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn latch_request(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_latch_request(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_latch_confirm(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_latch_heartbeat(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        unsafe { uapi::dtx_latch_cancel(self.as_raw_fd()) }?;
+        Ok(())
+    }
+
+    fn get_base_info(&self) -> std::io::Result<uapi::BaseInfo> {
+        let mut info = uapi::BaseInfo {
+            state: 0,
+            base_id: 0,
+        };
+
+        unsafe { uapi::dtx_get_base_info(self.as_raw_fd(), &mut info as *mut uapi::BaseInfo) }?;
+        Ok(info)
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        let mut mode: u16 = 0;
+
+        unsafe { uapi::dtx_get_device_mode(self.as_raw_fd(), &mut mode as *mut u16) }?;
+        Ok(mode)
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        let mut status: u16 = 0;
+
+        unsafe { uapi::dtx_get_latch_status(self.as_raw_fd(), &mut status as *mut u16) }?;
+        Ok(status)
+    }
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match nix::unistd::read(self.as_raw_fd(), buf) {
+                Err(nix::errno::Errno::EINTR) => continue,
+                result => return Ok(result?),
+            }
+        }
+    }
+}
@@ -0,0 +1,528 @@
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::io::AsyncRead;
+
+use nix::errno::Errno;
+
+use tracing::warn;
+
+use crate::uapi;
+use crate::DtxBackend;
+
+
+const MAGIC: &[u8; 8] = b"SDTXCAP1";
+
+const TAG_READ: u8 = 0;
+const TAG_IOCTL: u8 = 1;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Ioctl {
+    EventsEnable,
+    EventsDisable,
+    LatchLock,
+    LatchUnlock,
+    LatchRequest,
+    LatchConfirm,
+    LatchHeartbeat,
+    LatchCancel,
+    GetBaseInfo,
+    GetDeviceMode,
+    GetLatchStatus,
+}
+
+impl Ioctl {
+    pub fn nr(self) -> u8 {
+        match self {
+            Ioctl::EventsEnable   => 0x21,
+            Ioctl::EventsDisable  => 0x22,
+            Ioctl::LatchLock      => 0x23,
+            Ioctl::LatchUnlock    => 0x24,
+            Ioctl::LatchRequest   => 0x25,
+            Ioctl::LatchConfirm   => 0x26,
+            Ioctl::LatchHeartbeat => 0x27,
+            Ioctl::LatchCancel    => 0x28,
+            Ioctl::GetBaseInfo    => 0x29,
+            Ioctl::GetDeviceMode  => 0x2a,
+            Ioctl::GetLatchStatus => 0x2b,
+        }
+    }
+
+    pub fn from_nr(nr: u8) -> Option<Self> {
+        match nr {
+            0x21 => Some(Ioctl::EventsEnable),
+            0x22 => Some(Ioctl::EventsDisable),
+            0x23 => Some(Ioctl::LatchLock),
+            0x24 => Some(Ioctl::LatchUnlock),
+            0x25 => Some(Ioctl::LatchRequest),
+            0x26 => Some(Ioctl::LatchConfirm),
+            0x27 => Some(Ioctl::LatchHeartbeat),
+            0x28 => Some(Ioctl::LatchCancel),
+            0x29 => Some(Ioctl::GetBaseInfo),
+            0x2a => Some(Ioctl::GetDeviceMode),
+            0x2b => Some(Ioctl::GetLatchStatus),
+            _ => None,
+        }
+    }
+
+    fn is_query(self) -> bool {
+        matches!(self, Ioctl::GetBaseInfo | Ioctl::GetDeviceMode | Ioctl::GetLatchStatus)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    Read {
+        time: Duration,
+        data: Vec<u8>,
+    },
+
+    Ioctl {
+        time: Duration,
+        ioctl: Ioctl,
+        result: Result<u32, i32>,
+    },
+}
+
+impl Record {
+    pub fn time(&self) -> Duration {
+        match self {
+            Record::Read { time, .. } => *time,
+            Record::Ioctl { time, .. } => *time,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let time = u64::try_from(self.time().as_nanos()).unwrap_or(u64::MAX);
+
+        match self {
+            Record::Read { data, .. } => {
+                let len = u32::try_from(data.len())
+                    .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "read too large"))?;
+
+                writer.write_all(&[TAG_READ])?;
+                writer.write_all(&time.to_le_bytes())?;
+                writer.write_all(&len.to_le_bytes())?;
+                writer.write_all(data)?;
+            },
+            Record::Ioctl { ioctl, result, .. } => {
+                let (errno, value) = match *result {
+                    Ok(value) => (0, value),
+                    Err(errno) => (errno, 0),
+                };
+
+                writer.write_all(&[TAG_IOCTL])?;
+                writer.write_all(&time.to_le_bytes())?;
+                writer.write_all(&[ioctl.nr()])?;
+                writer.write_all(&errno.to_le_bytes())?;
+                writer.write_all(&value.to_le_bytes())?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Read the next record, returning `None` at the end of the capture.
+    pub fn read_from<R: Read>(reader: &mut R) -> std::io::Result<Option<Self>> {
+        let mut tag = [0; 1];
+        if reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+
+        let time = Duration::from_nanos(u64::from_le_bytes(read_array(reader)?));
+
+        match tag[0] {
+            TAG_READ => {
+                let len = u32::from_le_bytes(read_array(reader)?);
+
+                // don't trust the length for allocation, the capture may be truncated
+                let mut data = Vec::new();
+                reader.by_ref().take(len as u64).read_to_end(&mut data)?;
+
+                if data.len() != len as usize {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+
+                Ok(Some(Record::Read { time, data }))
+            },
+            TAG_IOCTL => {
+                let [nr] = read_array(reader)?;
+                let errno = i32::from_le_bytes(read_array(reader)?);
+                let value = u32::from_le_bytes(read_array(reader)?);
+
+                let ioctl = Ioctl::from_nr(nr)
+                    .ok_or_else(|| invalid_data(format!("unknown ioctl number: {nr:#04x}")))?;
+
+                let result = if errno == 0 { Ok(value) } else { Err(errno) };
+
+                Ok(Some(Record::Ioctl { time, ioctl, result }))
+            },
+            tag => Err(invalid_data(format!("unknown record tag: {tag:#04x}"))),
+        }
+    }
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> std::io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+
+#[derive(Debug)]
+struct Sink<W> {
+    writer: Option<W>,
+    start: Instant,
+}
+
+/// Backend wrapper recording all data read and all ioctl results.
+///
+/// Captures use the following binary format, with all integers stored in
+/// little-endian byte order and event data stored as read from the device:
+///
+/// - the magic bytes `SDTXCAP1`,
+/// - followed by records, each starting with a tag byte and the time since
+///   the start of the recording in nanoseconds (`u64`):
+///   - tag `0`, read: data length (`u32`), followed by the data read,
+///   - tag `1`, ioctl: ioctl number (`u8`, the `nr` field of the ioctl code),
+///     error number (`i32`, zero on success), returned value (`u32`).
+///
+/// The value returned by `dtx_get_base_info` is stored as `state | base_id << 16`.
+///
+/// Only successful reads are recorded. Failed reads, including `WouldBlock`
+/// on non-blocking files, are left out of the capture.
+///
+/// Failing to write the capture does not affect the wrapped backend. The
+/// error is logged and recording stops.
+#[derive(Debug)]
+pub struct Recorder<F, W: Write> {
+    backend: F,
+    sink: Mutex<Sink<W>>,
+}
+
+impl<F> Recorder<F, BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(backend: F, path: P) -> std::io::Result<Self> {
+        Recorder::new(backend, BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<F, W: Write> Recorder<F, W> {
+    pub fn new(backend: F, mut writer: W) -> std::io::Result<Self> {
+        writer.write_all(MAGIC)?;
+
+        let sink = Sink { writer: Some(writer), start: Instant::now() };
+
+        Ok(Recorder { backend, sink: Mutex::new(sink) })
+    }
+
+    pub fn backend(&self) -> &F {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut F {
+        &mut self.backend
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.sink.lock().unwrap().writer.is_some()
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        match self.sink.lock().unwrap().writer.as_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Flush the capture and return the backend and capture writer.
+    ///
+    /// The writer is `None` if recording has stopped due to an error.
+    pub fn into_inner(self) -> std::io::Result<(F, Option<W>)> {
+        let mut writer = self.sink.into_inner().unwrap().writer;
+
+        if let Some(writer) = writer.as_mut() {
+            writer.flush()?;
+        }
+
+        Ok((self.backend, writer))
+    }
+
+    fn record_read(&self, data: &[u8]) {
+        self.record(|time| Record::Read { time, data: data.into() })
+    }
+
+    fn record_ioctl<T>(&self, ioctl: Ioctl, result: &std::io::Result<T>, value: impl FnOnce(&T) -> u32) {
+        let result = match result {
+            Ok(v) => Ok(value(v)),
+            Err(e) => Err(e.raw_os_error().unwrap_or(Errno::EIO as i32)),
+        };
+
+        self.record(|time| Record::Ioctl { time, ioctl, result })
+    }
+
+    fn record(&self, record: impl FnOnce(Duration) -> Record) {
+        let mut sink = self.sink.lock().unwrap();
+        let time = sink.start.elapsed();
+
+        if let Some(writer) = sink.writer.as_mut() {
+            if let Err(err) = record(time).write_to(writer) {
+                warn!(target: "sdtx::capture", error=%err, "failed to write capture, recording stopped");
+                sink.writer = None;
+            }
+        }
+    }
+}
+
+impl<F: DtxBackend, W: Write> DtxBackend for Recorder<F, W> {
+    fn events_enable(&self) -> std::io::Result<()> {
+        let result = self.backend.events_enable();
+        self.record_ioctl(Ioctl::EventsEnable, &result, |_| 0);
+        result
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        let result = self.backend.events_disable();
+        self.record_ioctl(Ioctl::EventsDisable, &result, |_| 0);
+        result
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        let result = self.backend.latch_lock();
+        self.record_ioctl(Ioctl::LatchLock, &result, |_| 0);
+        result
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        let result = self.backend.latch_unlock();
+        self.record_ioctl(Ioctl::LatchUnlock, &result, |_| 0);
+        result
+    }
+
+    fn latch_request(&self) -> std::io::Result<()> {
+        let result = self.backend.latch_request();
+        self.record_ioctl(Ioctl::LatchRequest, &result, |_| 0);
+        result
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        let result = self.backend.latch_confirm();
+        self.record_ioctl(Ioctl::LatchConfirm, &result, |_| 0);
+        result
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        let result = self.backend.latch_heartbeat();
+        self.record_ioctl(Ioctl::LatchHeartbeat, &result, |_| 0);
+        result
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        let result = self.backend.latch_cancel();
+        self.record_ioctl(Ioctl::LatchCancel, &result, |_| 0);
+        result
+    }
+
+    fn get_base_info(&self) -> std::io::Result<uapi::BaseInfo> {
+        let result = self.backend.get_base_info();
+        self.record_ioctl(Ioctl::GetBaseInfo, &result, |info| info.state as u32 | (info.base_id as u32) << 16);
+        result
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        let result = self.backend.get_device_mode();
+        self.record_ioctl(Ioctl::GetDeviceMode, &result, |mode| *mode as u32);
+        result
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        let result = self.backend.get_latch_status();
+        self.record_ioctl(Ioctl::GetLatchStatus, &result, |status| *status as u32);
+        result
+    }
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.backend.read_events(buf)?;
+        self.record_read(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<F: AsyncRead + Unpin, W: Write + Unpin> AsyncRead for Recorder<F, W> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+        let n = futures::ready!(Pin::new(&mut self.backend).poll_read(cx, buf))?;
+        self.record_read(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// Exposes the file descriptor of the backend, e.g. for polling.
+impl<F: AsFd, W: Write> AsFd for Recorder<F, W> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.backend.as_fd()
+    }
+}
+
+impl<F: AsRawFd, W: Write> AsRawFd for Recorder<F, W> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.backend.as_raw_fd()
+    }
+}
+
+
+/// Backend playing back a capture.
+///
+/// Reads return the recorded data, split in the same way as it has originally
+/// been read, and signal end-of-file once all data has been consumed. Ioctls
+/// return the recorded results in order per ioctl, independent of reads.
+/// Commands without recorded result succeed, queries without recorded result
+/// fail with `ENODATA`. Timing is not reproduced.
+#[derive(Debug)]
+pub struct Replay {
+    reads: VecDeque<Vec<u8>>,
+    offset: usize,
+    ioctls: Mutex<HashMap<Ioctl, VecDeque<Result<u32, i32>>>>,
+}
+
+impl Replay {
+    pub fn new<I: IntoIterator<Item=Record>>(records: I) -> Self {
+        let mut reads = VecDeque::new();
+        let mut ioctls: HashMap<_, VecDeque<_>> = HashMap::new();
+
+        for record in records {
+            match record {
+                Record::Read { data, .. } => reads.push_back(data),
+                Record::Ioctl { ioctl, result, .. } => ioctls.entry(ioctl).or_default().push_back(result),
+            }
+        }
+
+        Replay { reads, offset: 0, ioctls: Mutex::new(ioctls) }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Replay::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let magic: [u8; 8] = read_array(&mut reader)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an sdtx capture".into()));
+        }
+
+        let mut records = Vec::new();
+        while let Some(record) = Record::read_from(&mut reader)? {
+            records.push(record);
+        }
+
+        Ok(Replay::new(records))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.reads.is_empty()
+    }
+
+    fn ioctl(&self, ioctl: Ioctl) -> std::io::Result<u32> {
+        let result = self.ioctls.lock().unwrap()
+            .get_mut(&ioctl)
+            .and_then(|results| results.pop_front());
+
+        match result {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(errno)) => Err(std::io::Error::from_raw_os_error(errno)),
+            None if ioctl.is_query() => Err(Errno::ENODATA.into()),
+            None => Ok(0),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let data = match self.reads.front() {
+            Some(data) => data,
+            None => return 0,
+        };
+
+        let n = usize::min(data.len() - self.offset, buf.len());
+        buf[..n].copy_from_slice(&data[self.offset..self.offset + n]);
+        self.offset += n;
+
+        if self.offset == data.len() {
+            self.reads.pop_front();
+            self.offset = 0;
+        }
+
+        n
+    }
+}
+
+impl DtxBackend for Replay {
+    fn events_enable(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::EventsEnable).map(|_| ())
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::EventsDisable).map(|_| ())
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::LatchLock).map(|_| ())
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::LatchUnlock).map(|_| ())
+    }
+
+    fn latch_request(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::LatchRequest).map(|_| ())
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::LatchConfirm).map(|_| ())
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::LatchHeartbeat).map(|_| ())
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        self.ioctl(Ioctl::LatchCancel).map(|_| ())
+    }
+
+    fn get_base_info(&self) -> std::io::Result<uapi::BaseInfo> {
+        let value = self.ioctl(Ioctl::GetBaseInfo)?;
+
+        Ok(uapi::BaseInfo {
+            state: value as u16,
+            base_id: (value >> 16) as u16,
+        })
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        self.ioctl(Ioctl::GetDeviceMode).map(|value| value as u16)
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        self.ioctl(Ioctl::GetLatchStatus).map(|value| value as u16)
+    }
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.read(buf))
+    }
+}
+
+impl AsyncRead for Replay {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(Ok(self.get_mut().read(buf)))
+    }
+}
@@ -1,17 +1,33 @@
+use std::borrow::BorrowMut;
 use std::convert::{TryFrom, TryInto};
-use std::io::{BufReader, Read};
-use std::os::unix::io::AsRawFd;
+use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Instant;
 
-use futures::{AsyncRead, AsyncReadExt, Stream};
-use smallvec::SmallVec;
+use futures::{AsyncRead, Stream};
+use smallvec::{smallvec, SmallVec};
 
 use crate::uapi;
-use crate::{Device, DeviceType, HardwareError, ProtocolError, RuntimeError};
+use crate::{Device, DeviceState, DeviceType, DtxBackend, Error, HardwareError, ProtocolError, RuntimeError};
+
+
+mod decode;
+pub use decode::{DecodeMode, EventDecoder, DEFAULT_MAX_PAYLOAD_LEN};
+
+mod filter;
+pub use filter::{EventFilterExt, Filtered};
+
+mod resync;
+pub use resync::{Resynced, ResyncedEvent};
+
+mod timestamp;
+pub use timestamp::{LastReadTime, Timestamped, TimestampedEvent};
 
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "event", rename_all = "kebab-case"))]
 pub enum Event {
     Request,
 
@@ -39,6 +55,21 @@ pub enum Event {
     },
 }
 
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Event::Request => write!(f, "Request"),
+            Event::Cancel { reason } => write!(f, "Cancel: {reason}"),
+            Event::BaseConnection { state, device_type, id } => {
+                write!(f, "BaseConnection: {state} (type: {device_type}, id: {id:#04x})")
+            },
+            Event::LatchStatus { status } => write!(f, "LatchStatus: {status}"),
+            Event::DeviceMode { mode } => write!(f, "DeviceMode: {mode}"),
+            Event::Unknown { code, data } => write!(f, "Unknown: code {code:#06x}, data {data:02x?}"),
+        }
+    }
+}
+
 impl Event {
     pub fn from_data(code: u16, data: &[u8]) -> Self {
         match code {
@@ -74,6 +105,11 @@ impl Event {
                 let base = &data[std::mem::size_of::<u16>()..2 * std::mem::size_of::<u16>()];
                 let base = u16::from_ne_bytes(base.try_into().unwrap());
 
+                // reserved bits cannot be represented, keep the raw data instead
+                if base & !(uapi::SDTX_DEVICE_TYPE_MASK | 0x00ff) != 0 {
+                    return Event::Unknown { code, data: data.into() };
+                }
+
                 let device_type = DeviceType::from(base);
                 let id = (base & 0xff) as u8;
 
@@ -107,16 +143,95 @@ impl Event {
             code => Event::Unknown { code, data: data.into() },
         }
     }
+
+    pub fn code(&self) -> u16 {
+        match self {
+            Event::Request               => uapi::SDTX_EVENT_REQUEST,
+            Event::Cancel { .. }         => uapi::SDTX_EVENT_CANCEL,
+            Event::BaseConnection { .. } => uapi::SDTX_EVENT_BASE_CONNECTION,
+            Event::LatchStatus { .. }    => uapi::SDTX_EVENT_LATCH_STATUS,
+            Event::DeviceMode { .. }     => uapi::SDTX_EVENT_DEVICE_MODE,
+            Event::Unknown { code, .. }  => *code,
+        }
+    }
+
+    fn payload(&self) -> SmallVec<[u8; 32]> {
+        let values: SmallVec<[u16; 2]> = match self {
+            Event::Request => SmallVec::new(),
+            Event::Cancel { reason } => smallvec![(*reason).into()],
+            Event::BaseConnection { state, device_type, id } => {
+                smallvec![(*state).into(), device_type.base_id(*id)]
+            },
+            Event::LatchStatus { status } => smallvec![(*status).into()],
+            Event::DeviceMode { mode } => smallvec![(*mode).into()],
+            Event::Unknown { data, .. } => {
+                return data[..usize::min(data.len(), u16::MAX as usize)].into();
+            },
+        };
+
+        values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+
+    /// Return the event as it is decoded after encoding it.
+    ///
+    /// Unknown values that alias a known one, e.g. `DeviceMode::Unknown(0)`
+    /// or an [`Event::Unknown`] with a known code and valid payload, are
+    /// replaced by the known variant. Values that cannot be encoded are
+    /// truncated, see [`Event::to_bytes`]. Events produced by the decoder are
+    /// returned unchanged.
+    pub fn normalized(&self) -> Event {
+        Event::from_data(self.code(), &self.payload())
+    }
+
+    /// Encode the event as read from the device, including its header.
+    ///
+    /// Decoding the result via [`Event::from_data`] gives back
+    /// [`Event::normalized`], i.e. the original event for any event produced
+    /// by the decoder.
+    ///
+    /// Values exceeding the wire format are truncated: only the low four bits
+    /// of a `DeviceType::Unknown` are encoded, as done by
+    /// [`DeviceType::base_id`], and only the first `u16::MAX` bytes of the
+    /// payload of an [`Event::Unknown`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf);
+        buf
+    }
+
+    /// Append the encoded event, including its header, to `buf`.
+    ///
+    /// See [`Event::to_bytes`].
+    pub fn encode_into(&self, buf: &mut Vec<u8>) {
+        let payload = self.payload();
+
+        buf.reserve(std::mem::size_of::<uapi::EventHeader>() + payload.len());
+        buf.extend_from_slice(&(payload.len() as u16).to_ne_bytes());
+        buf.extend_from_slice(&self.code().to_ne_bytes());
+        buf.extend_from_slice(&payload);
+    }
 }
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum CancelReason {
     Runtime(RuntimeError),
     Hardware(HardwareError),
     Unknown(u16),
 }
 
+impl std::fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CancelReason::Runtime(err)  => write!(f, "{err}"),
+            CancelReason::Hardware(err) => write!(f, "{err}"),
+            CancelReason::Unknown(v)    => write!(f, "Unknown ({v:#06x})"),
+        }
+    }
+}
+
 impl From<u16> for CancelReason {
     fn from(value: u16) -> Self {
         use uapi::*;
@@ -125,15 +240,17 @@ impl From<u16> for CancelReason {
             SDTX_CATEGORY_RUNTIME_ERROR => match value {
                 SDTX_DETACH_NOT_FEASIBLE       => Self::Runtime(RuntimeError::NotFeasible),
                 SDTX_DETACH_TIMEOUT            => Self::Runtime(RuntimeError::Timeout),
-                x                              => Self::Runtime(RuntimeError::Unknown(x as u8)),
+                x if crate::is_plain_error(x)  => Self::Runtime(RuntimeError::Unknown(x as u8)),
+                x                              => Self::Unknown(x),
             },
             SDTX_CATEGORY_HARDWARE_ERROR => match value {
                 SDTX_ERR_FAILED_TO_OPEN        => Self::Hardware(HardwareError::FailedToOpen),
                 SDTX_ERR_FAILED_TO_REMAIN_OPEN => Self::Hardware(HardwareError::FailedToRemainOpen),
                 SDTX_ERR_FAILED_TO_CLOSE       => Self::Hardware(HardwareError::FailedToClose),
-                x                              => Self::Hardware(HardwareError::Unknown(x as u8)),
+                x if crate::is_plain_error(x)  => Self::Hardware(HardwareError::Unknown(x as u8)),
+                x                              => Self::Unknown(x),
             },
-            x => Self::Unknown(x),
+            _ => Self::Unknown(value),
         }
     }
 }
@@ -150,8 +267,29 @@ impl TryFrom<CancelReason> for super::CancelReason {
     }
 }
 
+impl From<super::CancelReason> for CancelReason {
+    fn from(value: super::CancelReason) -> Self {
+        match value {
+            super::CancelReason::Runtime(err)  => Self::Runtime(err),
+            super::CancelReason::Hardware(err) => Self::Hardware(err),
+        }
+    }
+}
+
+impl From<CancelReason> for u16 {
+    fn from(value: CancelReason) -> Self {
+        match value {
+            CancelReason::Runtime(err)  => err.into(),
+            CancelReason::Hardware(err) => err.into(),
+            CancelReason::Unknown(v)    => v,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum BaseState {
     Detached,
     Attached,
@@ -159,6 +297,17 @@ pub enum BaseState {
     Unknown(u16),
 }
 
+impl std::fmt::Display for BaseState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BaseState::Detached    => write!(f, "Detached"),
+            BaseState::Attached    => write!(f, "Attached"),
+            BaseState::NotFeasible => write!(f, "NotFeasible"),
+            BaseState::Unknown(v)  => write!(f, "Unknown ({v:#06x})"),
+        }
+    }
+}
+
 impl From<u16> for BaseState {
     fn from(value: u16) -> Self {
         match value {
@@ -183,8 +332,31 @@ impl TryFrom<BaseState> for super::BaseState {
     }
 }
 
+impl From<super::BaseState> for BaseState {
+    fn from(value: super::BaseState) -> Self {
+        match value {
+            super::BaseState::Detached    => Self::Detached,
+            super::BaseState::Attached    => Self::Attached,
+            super::BaseState::NotFeasible => Self::NotFeasible,
+        }
+    }
+}
+
+impl From<BaseState> for u16 {
+    fn from(value: BaseState) -> Self {
+        match value {
+            BaseState::Detached    => uapi::SDTX_BASE_DETACHED,
+            BaseState::Attached    => uapi::SDTX_BASE_ATTACHED,
+            BaseState::NotFeasible => uapi::SDTX_DETACH_NOT_FEASIBLE,
+            BaseState::Unknown(v)  => v,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum LatchStatus {
     Closed,
     Opened,
@@ -192,6 +364,17 @@ pub enum LatchStatus {
     Unknown(u16),
 }
 
+impl std::fmt::Display for LatchStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LatchStatus::Closed     => write!(f, "Closed"),
+            LatchStatus::Opened     => write!(f, "Opened"),
+            LatchStatus::Error(err) => write!(f, "Error: {err}"),
+            LatchStatus::Unknown(v) => write!(f, "Unknown ({v:#06x})"),
+        }
+    }
+}
+
 impl From<u16> for LatchStatus {
     fn from(value: u16) -> Self {
         use uapi::*;
@@ -201,14 +384,15 @@ impl From<u16> for LatchStatus {
                 SDTX_ERR_FAILED_TO_OPEN        => Self::Error(HardwareError::FailedToOpen),
                 SDTX_ERR_FAILED_TO_REMAIN_OPEN => Self::Error(HardwareError::FailedToRemainOpen),
                 SDTX_ERR_FAILED_TO_CLOSE       => Self::Error(HardwareError::FailedToClose),
-                x                              => Self::Error(HardwareError::Unknown(x as u8)),
+                x if crate::is_plain_error(x)  => Self::Error(HardwareError::Unknown(x as u8)),
+                x                              => Self::Unknown(x),
             },
             SDTX_CATEGORY_STATUS => match value {
                 SDTX_LATCH_CLOSED => Self::Closed,
                 SDTX_LATCH_OPENED => Self::Opened,
                 x => Self::Unknown(x),
             },
-            x => Self::Unknown(x),
+            _ => Self::Unknown(value),
         }
     }
 }
@@ -226,8 +410,31 @@ impl TryFrom<LatchStatus> for super::LatchStatus {
     }
 }
 
+impl From<super::LatchStatus> for LatchStatus {
+    fn from(value: super::LatchStatus) -> Self {
+        match value {
+            super::LatchStatus::Closed     => Self::Closed,
+            super::LatchStatus::Opened     => Self::Opened,
+            super::LatchStatus::Error(err) => Self::Error(err),
+        }
+    }
+}
+
+impl From<LatchStatus> for u16 {
+    fn from(value: LatchStatus) -> Self {
+        match value {
+            LatchStatus::Closed     => uapi::SDTX_LATCH_CLOSED,
+            LatchStatus::Opened     => uapi::SDTX_LATCH_OPENED,
+            LatchStatus::Error(err) => err.into(),
+            LatchStatus::Unknown(v) => v,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum DeviceMode {
     Tablet,
     Laptop,
@@ -235,6 +442,17 @@ pub enum DeviceMode {
     Unknown(u16),
 }
 
+impl std::fmt::Display for DeviceMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DeviceMode::Tablet     => write!(f, "Tablet"),
+            DeviceMode::Laptop     => write!(f, "Laptop"),
+            DeviceMode::Studio     => write!(f, "Studio"),
+            DeviceMode::Unknown(v) => write!(f, "Unknown ({v:#06x})"),
+        }
+    }
+}
+
 impl From<u16> for DeviceMode {
     fn from(value: u16) -> Self {
         match value {
@@ -259,137 +477,333 @@ impl TryFrom<DeviceMode> for super::DeviceMode {
     }
 }
 
+impl From<super::DeviceMode> for DeviceMode {
+    fn from(value: super::DeviceMode) -> Self {
+        match value {
+            super::DeviceMode::Tablet => Self::Tablet,
+            super::DeviceMode::Laptop => Self::Laptop,
+            super::DeviceMode::Studio => Self::Studio,
+        }
+    }
+}
 
+impl From<DeviceMode> for u16 {
+    fn from(value: DeviceMode) -> Self {
+        match value {
+            DeviceMode::Tablet     => uapi::SDTX_DEVICE_MODE_TABLET,
+            DeviceMode::Laptop     => uapi::SDTX_DEVICE_MODE_LAPTOP,
+            DeviceMode::Studio     => uapi::SDTX_DEVICE_MODE_STUDIO,
+            DeviceMode::Unknown(v) => v,
+        }
+    }
+}
+
+
+/// Backend and decoder state shared by all event streams.
+///
+/// Events are enabled on creation and disabled again when dropped. All
+/// progress is kept in the decoder, so an asynchronous read can be abandoned
+/// at any point and continued later without losing data. New data is only
+/// read once all buffered events have been taken out, so every event has been
+/// completed by the last read.
 #[derive(Debug)]
-pub struct EventStream<'a, F: AsRawFd> {
-    reader: BufReader<&'a mut F>,
+struct Reader<F: DtxBackend, B: BorrowMut<F>> {
+    backend: B,
+    decoder: EventDecoder,
+    eof: bool,
+    read_at: Instant,
+    _marker: PhantomData<F>,
 }
 
-impl<'a, F: AsRawFd + Read> EventStream<'a, F> {
-    pub(crate) fn from_device(device: &'a mut Device<F>) -> std::io::Result<Self> {
-        device.events_enable()?;
+impl<F: DtxBackend, B: BorrowMut<F>> Reader<F, B> {
+    fn new(backend: B) -> Result<Self, Error> {
+        Device::from_backend(backend.borrow()).events_enable()?;
+
+        Ok(Reader {
+            backend,
+            decoder: EventDecoder::new(),
+            eof: false,
+            read_at: Instant::now(),
+            _marker: PhantomData,
+        })
+    }
 
-        let reader = BufReader::with_capacity(128, device.file_mut());
+    fn device(&self) -> &Device<F> {
+        Device::from_backend(self.backend.borrow())
+    }
 
-        Ok(EventStream { reader })
+    fn feed(&mut self, data: &[u8]) {
+        self.read_at = Instant::now();
+        self.decoder.feed(data);
     }
-}
 
-impl<F: AsRawFd> Drop for EventStream<'_, F> {
-    fn drop(&mut self) {
-        let _ = unsafe { uapi::dtx_events_disable(self.reader.get_ref().as_raw_fd()) };
+    fn read_blocking(&mut self) -> std::io::Result<Event> {
+        let mut buf = [0; 128];
+
+        loop {
+            if let Some(result) = self.decoder.next_event() {
+                return result;
+            }
+
+            match self.backend.borrow_mut().read_events(&mut buf) {
+                Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.feed(&buf[..n]),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn read_available(&mut self, events: &mut Vec<Event>) -> std::io::Result<usize> {
+        let mut buf = [0; 128];
+        let len = events.len();
+
+        loop {
+            for result in &mut self.decoder {
+                events.push(result?);
+            }
+
+            match self.backend.borrow_mut().read_events(&mut buf) {
+                Ok(0) => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.feed(&buf[..n]),
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return Ok(events.len() - len),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
     }
 }
 
-impl<F: AsRawFd + Read> EventStream<'_, F> {
-    pub fn read_next_blocking(&mut self) -> std::io::Result<Event> {
-        let mut buf_hdr = [0; std::mem::size_of::<uapi::EventHeader>()];
-        let mut buf_data = SmallVec::<[u8; 32]>::new();
+impl<F: DtxBackend + AsyncRead + Unpin, B: BorrowMut<F>> Reader<F, B> {
+    async fn read_next(&mut self) -> std::io::Result<Event> {
+        match futures::future::poll_fn(|cx| self.poll_next(cx)).await {
+            Some(result) => result,
+            None => Err(std::io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
 
-        self.reader.read_exact(&mut buf_hdr)?;
+    fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<std::io::Result<Event>>> {
+        let mut buf = [0; 128];
 
-        let hdr: uapi::EventHeader = unsafe { std::mem::transmute_copy(&buf_hdr) };
+        loop {
+            if let Some(result) = self.decoder.next_event() {
+                return Poll::Ready(Some(result));
+            }
 
-        buf_data.resize(hdr.length as usize, 0);
-        self.reader.read_exact(&mut buf_data)?;
+            if self.eof {
+                return Poll::Ready(None);
+            }
 
-        Ok(Event::from_data(hdr.code, &buf_data))
+            match futures::ready!(Pin::new(self.backend.borrow_mut()).poll_read(cx, &mut buf))? {
+                0 => {
+                    self.eof = true;
+
+                    if let Err(err) = self.decoder.finish() {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                },
+                n => self.feed(&buf[..n]),
+            }
+        }
     }
 }
 
-impl<F: AsRawFd + Read> Iterator for EventStream<'_, F> {
-    type Item = std::io::Result<Event>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        Some(self.read_next_blocking())
+impl<F: DtxBackend, B: BorrowMut<F>> Drop for Reader<F, B> {
+    fn drop(&mut self) {
+        let _ = self.device().events_disable();
     }
 }
 
 
+/// Methods shared by all event streams, which keep their state in a
+/// `reader` field.
+macro_rules! event_stream_methods {
+    (blocking) => {
+        pub fn read_next_blocking(&mut self) -> std::io::Result<Event> {
+            self.reader.read_blocking()
+        }
+
+        /// Read all currently available events without blocking, appending
+        /// them to `events`.
+        ///
+        /// Requires the device to be in non-blocking mode. Returns the number
+        /// of events appended. On error, events read before it are still
+        /// appended and reading can be continued afterwards.
+        pub fn try_read_events(&mut self, events: &mut Vec<Event>) -> std::io::Result<usize> {
+            self.reader.read_available(events)
+        }
+
+        event_stream_methods!();
+    };
+
+    (async) => {
+        /// Read the next event.
+        ///
+        /// This is cancel-safe: if the returned future is dropped before it
+        /// completes, no data is lost and the next call continues where it
+        /// left off. Fails with
+        /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) once the end
+        /// of the stream has been reached.
+        pub async fn read_next(&mut self) -> std::io::Result<Event> {
+            self.reader.read_next().await
+        }
+
+        event_stream_methods!();
+    };
+
+    () => {
+        /// The device events are read from.
+        pub fn device(&self) -> &Device<F> {
+            self.reader.device()
+        }
+
+        pub fn decode_mode(&self) -> DecodeMode {
+            self.reader.decoder.decode_mode()
+        }
+
+        pub fn set_decode_mode(&mut self, mode: DecodeMode) {
+            self.reader.decoder.set_decode_mode(mode);
+        }
+
+        pub fn with_decode_mode(mut self, mode: DecodeMode) -> Self {
+            self.reader.decoder.set_decode_mode(mode);
+            self
+        }
+
+        /// Stamp each event with the time its data has been read, see
+        /// [`Timestamped`].
+        pub fn timestamped(self) -> Timestamped<Self> {
+            Timestamped::new(self)
+        }
+
+        /// Resynchronize with the device state, see [`Resynced`].
+        pub fn resynced(self, last: Option<DeviceState>) -> Result<Resynced<Self>, Error> {
+            let current = DeviceState::query(self.device())?;
+            Ok(Resynced::new(self, current, last))
+        }
+    };
+}
+
+
 #[derive(Debug)]
-pub struct AsyncEventStream<'a, F: AsRawFd + AsyncRead + Unpin> {
-    file: &'a mut F,
-    buffer: Vec<u8>,
-    offset: usize,
+pub struct EventStream<'a, F: DtxBackend> {
+    reader: Reader<F, &'a mut F>,
 }
 
-impl<'a, F: AsRawFd + AsyncRead + Unpin> AsyncEventStream<'a, F> {
-    pub(crate) fn from_device(device: &'a mut Device<F>) -> std::io::Result<Self> {
-        device.events_enable()?;
+impl<'a, F: DtxBackend> EventStream<'a, F> {
+    pub(crate) fn from_device(device: &'a mut Device<F>) -> Result<Self, Error> {
+        Ok(EventStream { reader: Reader::new(device.file_mut())? })
+    }
+}
+
+impl<F: DtxBackend> EventStream<'_, F> {
+    event_stream_methods!(blocking);
+}
 
-        Ok(AsyncEventStream { file: device.file_mut(), buffer: vec![0; 128], offset: 0 })
+impl<F: DtxBackend> Iterator for EventStream<'_, F> {
+    type Item = std::io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.reader.read_blocking())
     }
 }
 
-impl<F: AsRawFd + AsyncRead + Unpin> Drop for AsyncEventStream<'_, F> {
-    fn drop(&mut self) {
-        let _ = unsafe { uapi::dtx_events_disable(self.file.as_raw_fd()) };
+impl<F: DtxBackend> LastReadTime for EventStream<'_, F> {
+    fn last_read_at(&self) -> Instant {
+        self.reader.read_at
     }
 }
 
-impl<F: AsRawFd + AsyncRead + Unpin> AsyncEventStream<'_, F> {
-    pub async fn read_next(&mut self) -> std::io::Result<Event> {
-        const HEADER_LEN: usize = std::mem::size_of::<uapi::EventHeader>();
 
-        while self.offset < HEADER_LEN {
-            self.offset += self.file.read(&mut self.buffer[self.offset..]).await?;
-        }
+#[derive(Debug)]
+pub struct OwnedEventStream<F: DtxBackend> {
+    reader: Reader<F, F>,
+}
 
-        let data_hdr = &self.buffer[..HEADER_LEN];
-        let data_hdr: [u8; HEADER_LEN] = data_hdr.try_into().unwrap();
-        let hdr: uapi::EventHeader = unsafe { std::mem::transmute_copy(&data_hdr) };
+impl<F: DtxBackend> OwnedEventStream<F> {
+    pub(crate) fn from_device(device: Device<F>) -> Result<Self, Error> {
+        Ok(OwnedEventStream { reader: Reader::new(device.into_file())? })
+    }
 
-        let event_len = HEADER_LEN + hdr.length as usize;
-        self.buffer.resize(event_len, 0);
+    event_stream_methods!(blocking);
+}
 
-        while self.offset < event_len {
-            self.offset += self.file.read(&mut self.buffer[self.offset..]).await?;
-        }
+impl<F: DtxBackend> Iterator for OwnedEventStream<F> {
+    type Item = std::io::Result<Event>;
 
-        let event = Event::from_data(hdr.code, &self.buffer[HEADER_LEN..event_len]);
-        self.offset = 0;
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.reader.read_blocking())
+    }
+}
 
-        Ok(event)
+impl<F: DtxBackend> LastReadTime for OwnedEventStream<F> {
+    fn last_read_at(&self) -> Instant {
+        self.reader.read_at
     }
 }
 
-impl<F: AsRawFd + AsyncRead + Unpin> Stream for AsyncEventStream<'_, F> {
-    type Item = std::io::Result<Event>;
 
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-        const HEADER_LEN: usize = std::mem::size_of::<uapi::EventHeader>();
+/// Asynchronous stream of events.
+///
+/// The stream ends when the device reaches end of file. If that happens in
+/// the middle of an event, an error of kind
+/// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) is returned first.
+#[derive(Debug)]
+pub struct AsyncEventStream<'a, F: DtxBackend + AsyncRead + Unpin> {
+    reader: Reader<F, &'a mut F>,
+}
 
-        let s = Pin::into_inner(self);
+impl<'a, F: DtxBackend + AsyncRead + Unpin> AsyncEventStream<'a, F> {
+    pub(crate) fn from_device(device: &'a mut Device<F>) -> Result<Self, Error> {
+        Ok(AsyncEventStream { reader: Reader::new(device.file_mut())? })
+    }
+}
 
-        if s.offset < HEADER_LEN {
-            s.offset += futures::ready!(Pin::new(&mut s.file)
-                .poll_read(cx, &mut s.buffer[s.offset..]))?;
-        }
+impl<F: DtxBackend + AsyncRead + Unpin> AsyncEventStream<'_, F> {
+    event_stream_methods!(async);
+}
 
-        if s.offset < HEADER_LEN {
-            return Poll::Pending;
-        }
+impl<F: DtxBackend + AsyncRead + Unpin> Stream for AsyncEventStream<'_, F> {
+    type Item = std::io::Result<Event>;
 
-        let data_hdr = &s.buffer[..HEADER_LEN];
-        let data_hdr: [u8; HEADER_LEN] = data_hdr.try_into().unwrap();
-        let hdr: uapi::EventHeader = unsafe { std::mem::transmute_copy(&data_hdr) };
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.reader.poll_next(cx)
+    }
+}
 
-        let event_len = HEADER_LEN + hdr.length as usize;
+impl<F: DtxBackend + AsyncRead + Unpin> LastReadTime for AsyncEventStream<'_, F> {
+    fn last_read_at(&self) -> Instant {
+        self.reader.read_at
+    }
+}
 
-        if s.offset < event_len {
-            s.buffer.resize(event_len, 0);
 
-            s.offset += futures::ready!(Pin::new(&mut s.file)
-                .poll_read(cx, &mut s.buffer[s.offset..]))?;
-        }
+/// Asynchronous stream of events, owning the device.
+///
+/// See [`AsyncEventStream`].
+#[derive(Debug)]
+pub struct OwnedAsyncEventStream<F: DtxBackend + AsyncRead + Unpin> {
+    reader: Reader<F, F>,
+}
 
-        if s.offset < event_len {
-            return Poll::Pending;
-        }
+impl<F: DtxBackend + AsyncRead + Unpin> OwnedAsyncEventStream<F> {
+    pub(crate) fn from_device(device: Device<F>) -> Result<Self, Error> {
+        Ok(OwnedAsyncEventStream { reader: Reader::new(device.into_file())? })
+    }
+
+    event_stream_methods!(async);
+}
+
+impl<F: DtxBackend + AsyncRead + Unpin> Stream for OwnedAsyncEventStream<F> {
+    type Item = std::io::Result<Event>;
 
-        let event = Event::from_data(hdr.code, &s.buffer[HEADER_LEN..event_len]);
-        s.offset = 0;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.reader.poll_next(cx)
+    }
+}
 
-        Poll::Ready(Some(Ok(event)))
+impl<F: DtxBackend + AsyncRead + Unpin> LastReadTime for OwnedAsyncEventStream<F> {
+    fn last_read_at(&self) -> Instant {
+        self.reader.read_at
     }
 }
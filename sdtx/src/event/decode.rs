@@ -0,0 +1,235 @@
+use tracing::warn;
+
+use super::{BaseState, CancelReason, DeviceMode, Event, LatchStatus};
+use crate::{uapi, ProtocolError};
+
+
+const HEADER_LEN: usize = std::mem::size_of::<uapi::EventHeader>();
+
+/// Default maximum payload length accepted by [`EventDecoder`].
+pub const DEFAULT_MAX_PAYLOAD_LEN: usize = 1024;
+
+
+/// How event streams handle events that cannot be fully decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeMode {
+    /// Return unknown events and values as-is, via their `Unknown` variants.
+    #[default]
+    Lenient,
+
+    /// Fail with a [`ProtocolError`] wrapped in an [`std::io::Error`] of kind
+    /// [`InvalidData`](std::io::ErrorKind::InvalidData).
+    Strict,
+
+    /// Skip unknown events and events with unknown values, logging them.
+    Lossy,
+}
+
+impl DecodeMode {
+    /// Apply the mode to a decoded event, returning `None` if the event
+    /// should be skipped.
+    pub fn apply(self, event: Event) -> std::io::Result<Option<Event>> {
+        if self == DecodeMode::Lenient {
+            return Ok(Some(event));
+        }
+
+        match event.validate() {
+            Ok(()) => Ok(Some(event)),
+            Err(err) if self == DecodeMode::Strict => {
+                Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+            },
+            Err(err) => {
+                warn!(target: "sdtx::event", error=%err, "skipping event");
+                Ok(None)
+            },
+        }
+    }
+}
+
+
+
+/// Decoder turning raw event data into events, independent of how the data
+/// is read.
+///
+/// Data is passed in via [`EventDecoder::feed`] in chunks of any size, events
+/// are taken out via [`EventDecoder::next_event`] or by iterating. Iteration
+/// stops when more data is needed and can be continued after feeding more.
+///
+/// Events with a payload longer than the maximum payload length are skipped
+/// and reported as [`ProtocolError::PayloadTooLarge`], unless the decode mode
+/// is [`DecodeMode::Lossy`], in which case they are only logged.
+#[derive(Debug, Clone)]
+pub struct EventDecoder {
+    buffer: Vec<u8>,
+    offset: usize,
+    skip: usize,
+    max_payload_len: usize,
+    mode: DecodeMode,
+}
+
+impl EventDecoder {
+    pub fn new() -> Self {
+        EventDecoder {
+            buffer: Vec::new(),
+            offset: 0,
+            skip: 0,
+            max_payload_len: DEFAULT_MAX_PAYLOAD_LEN,
+            mode: DecodeMode::default(),
+        }
+    }
+
+    pub fn decode_mode(&self) -> DecodeMode {
+        self.mode
+    }
+
+    pub fn set_decode_mode(&mut self, mode: DecodeMode) {
+        self.mode = mode;
+    }
+
+    pub fn with_decode_mode(mut self, mode: DecodeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn max_payload_len(&self) -> usize {
+        self.max_payload_len
+    }
+
+    pub fn set_max_payload_len(&mut self, len: usize) {
+        self.max_payload_len = len;
+    }
+
+    pub fn with_max_payload_len(mut self, len: usize) -> Self {
+        self.max_payload_len = len;
+        self
+    }
+
+    /// Add data read from the device.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buffer.drain(..self.offset);
+        self.offset = 0;
+
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Whether no partial event is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.offset == self.buffer.len() && self.skip == 0
+    }
+
+    /// Signal the end of the data, resetting the decoder.
+    ///
+    /// Fails with [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) if a
+    /// partial event has been discarded.
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        let empty = self.is_empty();
+
+        self.buffer.clear();
+        self.offset = 0;
+        self.skip = 0;
+
+        if empty {
+            Ok(())
+        } else {
+            Err(std::io::ErrorKind::UnexpectedEof.into())
+        }
+    }
+
+    /// Decode the next event, returning `None` if more data is needed.
+    pub fn next_event(&mut self) -> Option<std::io::Result<Event>> {
+        loop {
+            let event = match self.next_frame()? {
+                Ok(event) => event,
+                Err(err) if self.mode == DecodeMode::Lossy => {
+                    warn!(target: "sdtx::event", error=%err, "skipping event");
+                    continue;
+                },
+                Err(err) => return Some(Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err))),
+            };
+
+            match self.mode.apply(event) {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<Result<Event, ProtocolError>> {
+        if self.skip > 0 {
+            let n = usize::min(self.skip, self.buffer.len() - self.offset);
+
+            self.offset += n;
+            self.skip -= n;
+
+            if self.skip > 0 {
+                return None;
+            }
+        }
+
+        let data = &self.buffer[self.offset..];
+        if data.len() < HEADER_LEN {
+            return None;
+        }
+
+        let length = u16::from_ne_bytes([data[0], data[1]]);
+        let code = u16::from_ne_bytes([data[2], data[3]]);
+
+        if length as usize > self.max_payload_len {
+            self.offset += HEADER_LEN;
+            self.skip = length as usize;
+
+            return Some(Err(ProtocolError::PayloadTooLarge(length)));
+        }
+
+        let event_len = HEADER_LEN + length as usize;
+        if data.len() < event_len {
+            return None;
+        }
+
+        let event = Event::from_data(code, &data[HEADER_LEN..event_len]);
+        self.offset += event_len;
+
+        Some(Ok(event))
+    }
+}
+
+impl Default for EventDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for EventDecoder {
+    type Item = std::io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event()
+    }
+}
+
+
+impl Event {
+    /// Check that the event and all its values are known.
+    pub fn validate(&self) -> Result<(), ProtocolError> {
+        match self {
+            Event::Request => Ok(()),
+            Event::Cancel { reason: CancelReason::Unknown(v) } => Err(ProtocolError::InvalidCancelReason(*v)),
+            Event::Cancel { .. } => Ok(()),
+            Event::BaseConnection { state: BaseState::Unknown(v), .. } => Err(ProtocolError::InvalidBaseState(*v)),
+            Event::BaseConnection { .. } => Ok(()),
+            Event::LatchStatus { status: LatchStatus::Unknown(v) } => Err(ProtocolError::InvalidLatchStatus(*v)),
+            Event::LatchStatus { .. } => Ok(()),
+            Event::DeviceMode { mode: DeviceMode::Unknown(v) } => Err(ProtocolError::InvalidDeviceMode(*v)),
+            Event::DeviceMode { .. } => Ok(()),
+            Event::Unknown { code, .. } => match *code {
+                uapi::SDTX_EVENT_REQUEST
+                    | uapi::SDTX_EVENT_CANCEL
+                    | uapi::SDTX_EVENT_BASE_CONNECTION
+                    | uapi::SDTX_EVENT_LATCH_STATUS
+                    | uapi::SDTX_EVENT_DEVICE_MODE => Err(ProtocolError::InvalidEventPayload(*code)),
+                code => Err(ProtocolError::UnknownEvent(code)),
+            },
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::{AsyncRead, Stream};
+
+use super::{AsyncEventStream, Event, EventStream, OwnedAsyncEventStream, OwnedEventStream};
+use crate::{BaseInfo, BaseState, DeviceMode, DtxBackend, Error, LatchStatus, ProtocolError};
+
+
+type Select<T> = fn(Event) -> Option<Result<T, ProtocolError>>;
+
+
+/// Event stream adapter yielding only one kind of event, converted to its
+/// strict type.
+///
+/// Events with unknown values are returned as [`Error::ProtocolError`].
+#[derive(Debug)]
+pub struct Filtered<S, T> {
+    inner: S,
+    select: Select<T>,
+}
+
+impl<S, T> Filtered<S, T> {
+    fn new(inner: S, select: Select<T>) -> Self {
+        Filtered { inner, select }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, T> Iterator for Filtered<S, T>
+where
+    S: Iterator<Item=std::io::Result<Event>>,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.inner.next()? {
+                Ok(event) => if let Some(item) = (self.select)(event) {
+                    return Some(item.map_err(Error::from));
+                },
+                Err(err) => return Some(Err(stream_error(err))),
+            }
+        }
+    }
+}
+
+impl<S, T> Stream for Filtered<S, T>
+where
+    S: Stream<Item=std::io::Result<Event>> + Unpin,
+    T: Unpin,
+{
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let s = Pin::into_inner(self);
+
+        loop {
+            match futures::ready!(Pin::new(&mut s.inner).poll_next(cx)) {
+                Some(Ok(event)) => if let Some(item) = (s.select)(event) {
+                    return Poll::Ready(Some(item.map_err(Error::from)));
+                },
+                Some(Err(err)) => return Poll::Ready(Some(Err(stream_error(err)))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+
+fn stream_error(err: std::io::Error) -> Error {
+    // unwrap protocol errors reported by streams in strict decoding mode
+    match err.get_ref().and_then(|e| e.downcast_ref::<ProtocolError>()) {
+        Some(source) => Error::ProtocolError { source: *source },
+        None => Error::IoError { source: err },
+    }
+}
+
+
+/// Combinators selecting a single kind of event from an event stream.
+pub trait EventFilterExt: Sized {
+    fn device_mode_changes(self) -> Filtered<Self, DeviceMode> {
+        Filtered::new(self, |event| match event {
+            Event::DeviceMode { mode } => Some(DeviceMode::try_from(mode)),
+            _ => None,
+        })
+    }
+
+    fn base_connections(self) -> Filtered<Self, BaseInfo> {
+        Filtered::new(self, |event| match event {
+            Event::BaseConnection { state, device_type, id } => {
+                Some(BaseState::try_from(state).map(|state| BaseInfo { state, device_type, id }))
+            },
+            _ => None,
+        })
+    }
+
+    fn latch_status(self) -> Filtered<Self, LatchStatus> {
+        Filtered::new(self, |event| match event {
+            Event::LatchStatus { status } => Some(LatchStatus::try_from(status)),
+            _ => None,
+        })
+    }
+
+    fn detach_requests(self) -> Filtered<Self, ()> {
+        Filtered::new(self, |event| match event {
+            Event::Request => Some(Ok(())),
+            _ => None,
+        })
+    }
+}
+
+impl<F: DtxBackend> EventFilterExt for EventStream<'_, F> {}
+impl<F: DtxBackend> EventFilterExt for OwnedEventStream<F> {}
+impl<F: DtxBackend + AsyncRead + Unpin> EventFilterExt for AsyncEventStream<'_, F> {}
+impl<F: DtxBackend + AsyncRead + Unpin> EventFilterExt for OwnedAsyncEventStream<F> {}
@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+
+use super::Event;
+use crate::DeviceState;
+
+
+/// Event as returned by [`Resynced`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResyncedEvent {
+    pub event: Event,
+
+    /// Whether the event has been synthesized from a state difference instead
+    /// of being received from the device.
+    pub synthesized: bool,
+}
+
+
+/// Event stream adapter resynchronizing with the device state.
+///
+/// When created, the current device state is queried and compared to the
+/// last known state. For any difference, a synthesized event is emitted
+/// before any event received from the device. If there is no last known
+/// state, events are synthesized for base, device mode, and latch status.
+///
+/// The tracked state is updated with all events passing through and can be
+/// handed to the next stream via [`Resynced::state`], e.g. after the stream
+/// has been recreated. Whether a detachment is pending cannot be queried, so
+/// it is carried over from the last known state.
+#[derive(Debug)]
+pub struct Resynced<S> {
+    inner: S,
+    state: DeviceState,
+    synthesized: VecDeque<Event>,
+}
+
+impl<S> Resynced<S> {
+    pub(crate) fn new(inner: S, current: DeviceState, last: Option<DeviceState>) -> Self {
+        let synthesized = diff(last.as_ref(), &current);
+
+        let mut state = last.unwrap_or(current);
+        for event in &synthesized {
+            state.apply(event);
+        }
+
+        Resynced { inner, state, synthesized }
+    }
+
+    pub fn state(&self) -> &DeviceState {
+        &self.state
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn received(&mut self, event: Event) -> ResyncedEvent {
+        self.state.apply(&event);
+        ResyncedEvent { event, synthesized: false }
+    }
+}
+
+impl<S> Iterator for Resynced<S>
+where
+    S: Iterator<Item=std::io::Result<Event>>,
+{
+    type Item = std::io::Result<ResyncedEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.synthesized.pop_front() {
+            return Some(Ok(ResyncedEvent { event, synthesized: true }));
+        }
+
+        match self.inner.next()? {
+            Ok(event) => Some(Ok(self.received(event))),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+impl<S> Stream for Resynced<S>
+where
+    S: Stream<Item=std::io::Result<Event>> + Unpin,
+{
+    type Item = std::io::Result<ResyncedEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let s = Pin::into_inner(self);
+
+        if let Some(event) = s.synthesized.pop_front() {
+            return Poll::Ready(Some(Ok(ResyncedEvent { event, synthesized: true })));
+        }
+
+        match futures::ready!(Pin::new(&mut s.inner).poll_next(cx)) {
+            Some(Ok(event)) => Poll::Ready(Some(Ok(s.received(event)))),
+            Some(Err(err)) => Poll::Ready(Some(Err(err))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+
+fn diff(last: Option<&DeviceState>, current: &DeviceState) -> VecDeque<Event> {
+    let mut events = VecDeque::new();
+
+    if last.map(|s| s.base) != Some(current.base) {
+        events.push_back(Event::BaseConnection {
+            state: current.base.state.into(),
+            device_type: current.base.device_type,
+            id: current.base.id,
+        });
+    }
+
+    if last.map(|s| s.device_mode) != Some(current.device_mode) {
+        events.push_back(Event::DeviceMode { mode: current.device_mode.into() });
+    }
+
+    if last.map(|s| s.latch_status) != Some(current.latch_status) {
+        events.push_back(Event::LatchStatus { status: current.latch_status.into() });
+    }
+
+    events
+}
@@ -0,0 +1,90 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
+
+use super::Event;
+
+
+/// Event together with the time it has been received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimestampedEvent {
+    pub event: Event,
+    pub received_at: Instant,
+}
+
+impl TimestampedEvent {
+    /// Time passed between receiving `earlier` and this event.
+    pub fn duration_since(&self, earlier: &TimestampedEvent) -> Duration {
+        self.received_at.saturating_duration_since(earlier.received_at)
+    }
+}
+
+
+/// Event streams keeping track of when data has been read from the device.
+pub trait LastReadTime {
+    /// Time taken right after the last read returning data, i.e. the read
+    /// that completed the event returned last.
+    fn last_read_at(&self) -> Instant;
+}
+
+
+/// Event stream adapter stamping each event with the time its data has been
+/// read off the device.
+///
+/// Events decoded from the same read get the same timestamp, independent of
+/// when they are taken out of the stream. Works with both blocking
+/// ([`Iterator`]) and asynchronous ([`Stream`]) event streams.
+#[derive(Debug)]
+pub struct Timestamped<S> {
+    inner: S,
+}
+
+impl<S> Timestamped<S> {
+    pub fn new(inner: S) -> Self {
+        Timestamped { inner }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: LastReadTime> Timestamped<S> {
+    fn stamp(&self, event: Event) -> TimestampedEvent {
+        TimestampedEvent { event, received_at: self.inner.last_read_at() }
+    }
+}
+
+impl<S> Iterator for Timestamped<S>
+where
+    S: Iterator<Item=std::io::Result<Event>> + LastReadTime,
+{
+    type Item = std::io::Result<TimestampedEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        Some(item.map(|event| self.stamp(event)))
+    }
+}
+
+impl<S> Stream for Timestamped<S>
+where
+    S: Stream<Item=std::io::Result<Event>> + LastReadTime + Unpin,
+{
+    type Item = std::io::Result<TimestampedEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let item = futures::ready!(Pin::new(&mut self.inner).poll_next(cx));
+        Poll::Ready(item.map(|r| r.map(|event| self.stamp(event))))
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, warn};
+
+use crate::event::BaseState;
+use crate::{Device, DtxBackend, Event};
+
+
+#[derive(Debug, Default)]
+struct Inner {
+    active: bool,
+    closed: bool,
+    generation: u64,
+    wakers: HashMap<usize, Waker>,
+    next_runner: usize,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    inner: Mutex<Inner>,
+    changed: Condvar,
+}
+
+
+/// Keeps a pending detachment alive by periodically sending heartbeats.
+///
+/// Heartbeats are started by a detachment request and stopped once the
+/// request has been aborted, canceled, or the base has been detached. Events
+/// need to be passed in via [`Heartbeat::handle`]. The heartbeats themselves
+/// are sent either by [`Heartbeat::run`] or a [`HeartbeatThread`].
+#[derive(Debug, Clone, Default)]
+pub struct Heartbeat {
+    shared: Arc<Shared>,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle(&self, event: &Event) {
+        match event {
+            Event::Request if self.is_active() => self.stop(),
+            Event::Request => self.start(),
+            Event::Cancel { .. } => self.stop(),
+            Event::BaseConnection { state: BaseState::Detached, .. } => self.stop(),
+            _ => {},
+        }
+    }
+
+    pub fn start(&self) {
+        self.update(|inner| {
+            inner.active = true;
+            inner.generation += 1;
+        });
+
+        debug!(target: "sdtx::heartbeat", "heartbeat started");
+    }
+
+    pub fn stop(&self) {
+        let was_active = self.update(|inner| std::mem::replace(&mut inner.active, false));
+
+        if was_active {
+            debug!(target: "sdtx::heartbeat", "heartbeat stopped");
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.lock().active
+    }
+
+    /// Stop sending heartbeats permanently and let all runners exit.
+    pub fn close(&self) {
+        self.update(|inner| {
+            inner.active = false;
+            inner.closed = true;
+        });
+    }
+
+    /// Send heartbeats while active, using `sleep` to wait between them.
+    ///
+    /// Returns once [`Heartbeat::close`] has been called.
+    pub async fn run<F, S, Fut>(&self, device: &Device<F>, interval: Duration, mut sleep: S)
+    where
+        F: DtxBackend,
+        S: FnMut(Duration) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let runner = Runner::new(self);
+
+        loop {
+            let generation = futures::future::poll_fn(|cx| {
+                let mut inner = self.lock();
+
+                if inner.closed {
+                    Poll::Ready(None)
+                } else if inner.active {
+                    Poll::Ready(Some(inner.generation))
+                } else {
+                    inner.wakers.insert(runner.id, cx.waker().clone());
+                    Poll::Pending
+                }
+            });
+
+            let generation = match generation.await {
+                Some(generation) => generation,
+                None => return,
+            };
+
+            sleep(interval).await;
+
+            if self.is_current(generation) {
+                send(device);
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.shared.inner.lock().unwrap()
+    }
+
+    fn update<R>(&self, f: impl FnOnce(&mut Inner) -> R) -> R {
+        let mut inner = self.lock();
+
+        let result = f(&mut inner);
+
+        for (_, waker) in inner.wakers.drain() {
+            waker.wake();
+        }
+
+        self.shared.changed.notify_all();
+        result
+    }
+
+    fn is_current(&self, generation: u64) -> bool {
+        let inner = self.lock();
+        inner.active && !inner.closed && inner.generation == generation
+    }
+}
+
+
+/// Registration of a [`Heartbeat::run`] call, removing its waker once the
+/// call has returned or its future has been dropped.
+struct Runner<'a> {
+    heartbeat: &'a Heartbeat,
+    id: usize,
+}
+
+impl<'a> Runner<'a> {
+    fn new(heartbeat: &'a Heartbeat) -> Self {
+        let mut inner = heartbeat.lock();
+
+        let id = inner.next_runner;
+        inner.next_runner += 1;
+
+        Runner { heartbeat, id }
+    }
+}
+
+impl Drop for Runner<'_> {
+    fn drop(&mut self) {
+        self.heartbeat.lock().wakers.remove(&self.id);
+    }
+}
+
+
+/// Background thread sending heartbeats, stopped when dropped.
+#[derive(Debug)]
+pub struct HeartbeatThread {
+    heartbeat: Heartbeat,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HeartbeatThread {
+    pub fn spawn<F>(device: Arc<Device<F>>, interval: Duration) -> std::io::Result<Self>
+    where
+        F: DtxBackend + Send + Sync + 'static,
+    {
+        let heartbeat = Heartbeat::new();
+        let shared = heartbeat.shared.clone();
+
+        let thread = std::thread::Builder::new()
+            .name("sdtx-heartbeat".into())
+            .spawn(move || heartbeat_thread(&shared, &device, interval))?;
+
+        Ok(HeartbeatThread { heartbeat, thread: Some(thread) })
+    }
+
+    pub fn heartbeat(&self) -> &Heartbeat {
+        &self.heartbeat
+    }
+
+    pub fn handle(&self, event: &Event) {
+        self.heartbeat.handle(event)
+    }
+}
+
+impl Drop for HeartbeatThread {
+    fn drop(&mut self) {
+        self.heartbeat.close();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+
+fn heartbeat_thread<F: DtxBackend>(shared: &Shared, device: &Device<F>, interval: Duration) {
+    let mut inner = shared.inner.lock().unwrap();
+
+    loop {
+        if inner.closed {
+            return;
+        }
+
+        if !inner.active {
+            inner = shared.changed.wait(inner).unwrap();
+            continue;
+        }
+
+        let generation = inner.generation;
+        let deadline = Instant::now() + interval;
+
+        loop {
+            let now = Instant::now();
+
+            if inner.closed || !inner.active || inner.generation != generation || now >= deadline {
+                break;
+            }
+
+            inner = shared.changed.wait_timeout(inner, deadline - now).unwrap().0;
+        }
+
+        if inner.closed || !inner.active || inner.generation != generation {
+            continue;
+        }
+
+        drop(inner);
+        send(device);
+        inner = shared.inner.lock().unwrap();
+    }
+}
+
+fn send<F: DtxBackend>(device: &Device<F>) {
+    if let Err(err) = device.latch_heartbeat() {
+        warn!(target: "sdtx::heartbeat", error=%err, "failed to send heartbeat");
+    }
+}
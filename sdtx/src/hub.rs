@@ -0,0 +1,479 @@
+use std::collections::{HashMap, VecDeque};
+use std::os::unix::io::{AsFd, OwnedFd};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::JoinHandle;
+
+use futures::{Stream, StreamExt};
+
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::poll::{PollFd, PollFlags, PollTimeout};
+
+use tracing::{debug, warn};
+
+use crate::{Device, DtxBackend, Error, Event, OwnedEventStream};
+
+
+pub const DEFAULT_CAPACITY: usize = 64;
+
+
+/// What to do when a subscriber queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Drop the oldest queued event and count it as lagged.
+    DropOldest,
+
+    /// Wait with publishing until all subscribers have room again.
+    Backpressure,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    Closed,
+}
+
+impl std::fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TryRecvError::Empty  => write!(f, "No event available"),
+            TryRecvError::Closed => write!(f, "Event hub closed"),
+        }
+    }
+}
+
+impl std::error::Error for TryRecvError {}
+
+
+#[derive(Debug, Default)]
+struct Queue {
+    events: VecDeque<Event>,
+    lagged: u64,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    subscribers: HashMap<usize, Queue>,
+    next_id: usize,
+    closed: bool,
+    publishers: Vec<Waker>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+    capacity: usize,
+    policy: LagPolicy,
+    wake: Option<OwnedFd>,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn notify(&self, state: &mut State) {
+        for queue in state.subscribers.values_mut() {
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+
+        for waker in state.publishers.drain(..) {
+            waker.wake();
+        }
+
+        self.changed.notify_all();
+    }
+
+    fn notify_publishers(&self, state: &mut State) {
+        if self.policy != LagPolicy::Backpressure {
+            return;
+        }
+
+        for waker in state.publishers.drain(..) {
+            waker.wake();
+        }
+
+        self.changed.notify_all();
+    }
+
+    fn is_full(&self, state: &State) -> bool {
+        state.subscribers.values().any(|q| q.events.len() >= self.capacity)
+    }
+
+    fn push(&self, state: &mut State, event: &Event) {
+        for (id, queue) in state.subscribers.iter_mut() {
+            if queue.events.len() >= self.capacity {
+                queue.events.pop_front();
+                queue.lagged += 1;
+
+                debug!(target: "sdtx::hub", subscriber=id, lagged=queue.lagged, "subscriber lagging, dropped event");
+            }
+
+            queue.events.push_back(event.clone());
+        }
+
+        self.notify(state);
+    }
+}
+
+
+/// Distributes events from a single reader to any number of subscribers.
+///
+/// Events are fed in via [`EventHub::publish`], [`EventHub::forward`],
+/// [`EventHub::forward_blocking`], or by a reader thread started with
+/// [`EventHub::spawn`]. Each subscriber has its own queue with the capacity
+/// of the hub. Once the hub is closed, subscribers receive the remaining
+/// queued events and then end.
+#[derive(Debug, Clone)]
+pub struct EventHub {
+    shared: Arc<Shared>,
+}
+
+impl EventHub {
+    pub fn new(policy: LagPolicy) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, policy)
+    }
+
+    pub fn with_capacity(capacity: usize, policy: LagPolicy) -> Self {
+        Self::build(capacity, policy, None)
+    }
+
+    fn build(capacity: usize, policy: LagPolicy, wake: Option<OwnedFd>) -> Self {
+        assert!(capacity > 0, "event hub capacity must be non-zero");
+
+        let shared = Shared {
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+            capacity,
+            policy,
+            wake,
+        };
+
+        EventHub { shared: Arc::new(shared) }
+    }
+
+    /// Start a thread reading events from the device and publishing them.
+    ///
+    /// The device file is switched to non-blocking mode and polled together
+    /// with a wake-up pipe signalled by [`EventHub::close`], so the thread
+    /// stops as soon as the hub is closed, even on an idle device. If
+    /// reading fails, the hub is closed as well.
+    pub fn spawn<F>(device: Device<F>, capacity: usize, policy: LagPolicy) -> Result<HubThread, Error>
+    where
+        F: DtxBackend + AsFd + Send + 'static,
+    {
+        let (wake_rx, wake_tx) = nix::unistd::pipe2(OFlag::O_CLOEXEC | OFlag::O_NONBLOCK)
+            .map_err(std::io::Error::from)?;
+
+        device.set_nonblocking(true)?;
+
+        let hub = EventHub::build(capacity, policy, Some(wake_tx));
+        let events = device.into_events()?;
+
+        let reader = hub.clone();
+        let thread = std::thread::Builder::new()
+            .name("sdtx-event-hub".into())
+            .spawn(move || {
+                let result = reader.forward_polled(events, &wake_rx);
+                reader.close();
+
+                if let Err(ref err) = result {
+                    warn!(target: "sdtx::hub", error=%err, "failed to read events");
+                }
+
+                result
+            })?;
+
+        Ok(HubThread { hub, thread: Some(thread) })
+    }
+
+    fn forward_polled<F>(&self, mut events: OwnedEventStream<F>, wake: &OwnedFd) -> std::io::Result<()>
+    where
+        F: DtxBackend + AsFd,
+    {
+        let mut batch = Vec::new();
+
+        loop {
+            let mut fds = [
+                PollFd::new(events.device().file().as_fd(), PollFlags::POLLIN),
+                PollFd::new(wake.as_fd(), PollFlags::POLLIN),
+            ];
+
+            match nix::poll::poll(&mut fds, PollTimeout::NONE) {
+                Ok(_) => {},
+                Err(Errno::EINTR) => continue,
+                Err(err) => return Err(err.into()),
+            }
+
+            if fds[1].any().unwrap_or(false) {
+                return Ok(());
+            }
+
+            let result = events.try_read_events(&mut batch);
+
+            for event in batch.drain(..) {
+                if !self.publish(&event) {
+                    return Ok(());
+                }
+            }
+
+            result?;
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    pub fn policy(&self) -> LagPolicy {
+        self.shared.policy
+    }
+
+    pub fn subscribe(&self) -> Subscriber {
+        let mut state = self.shared.state();
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.subscribers.insert(id, Queue::default());
+
+        Subscriber { shared: self.shared.clone(), id }
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.shared.state().subscribers.len()
+    }
+
+    /// Publish an event to all subscribers, blocking on backpressure.
+    ///
+    /// Returns `false` if the hub has been closed.
+    pub fn publish(&self, event: &Event) -> bool {
+        let mut state = self.shared.state();
+
+        if self.shared.policy == LagPolicy::Backpressure {
+            while !state.closed && self.shared.is_full(&state) {
+                state = self.shared.changed.wait(state).unwrap();
+            }
+        }
+
+        if state.closed {
+            return false;
+        }
+
+        self.shared.push(&mut state, event);
+        true
+    }
+
+    /// Publish an event to all subscribers, waiting on backpressure.
+    ///
+    /// Returns `false` if the hub has been closed.
+    pub async fn publish_async(&self, event: &Event) -> bool {
+        futures::future::poll_fn(|cx| {
+            let mut state = self.shared.state();
+
+            if state.closed {
+                return Poll::Ready(false);
+            }
+
+            if self.shared.policy == LagPolicy::Backpressure && self.shared.is_full(&state) {
+                state.publishers.push(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            self.shared.push(&mut state, event);
+            Poll::Ready(true)
+        }).await
+    }
+
+    /// Publish all events of the given stream, then close the hub.
+    ///
+    /// Returns early if the hub has been closed or reading fails.
+    pub fn forward_blocking<I>(&self, events: I) -> std::io::Result<()>
+    where
+        I: IntoIterator<Item=std::io::Result<Event>>,
+    {
+        let mut result = Ok(());
+
+        for event in events {
+            match event {
+                Ok(event) if self.publish(&event) => {},
+                Ok(_) => break,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                },
+            }
+        }
+
+        self.close();
+        result
+    }
+
+    /// Publish all events of the given stream, then close the hub.
+    ///
+    /// Returns early if the hub has been closed or reading fails.
+    pub async fn forward<S>(&self, mut events: S) -> std::io::Result<()>
+    where
+        S: Stream<Item=std::io::Result<Event>> + Unpin,
+    {
+        let result = async {
+            while let Some(event) = events.next().await {
+                if !self.publish_async(&event?).await {
+                    break;
+                }
+            }
+
+            Ok(())
+        }.await;
+
+        self.close();
+        result
+    }
+
+    pub fn close(&self) {
+        let mut state = self.shared.state();
+
+        state.closed = true;
+        self.shared.notify(&mut state);
+
+        // Wake up the reader thread. If the pipe is full, it has already
+        // been woken up.
+        if let Some(wake) = &self.shared.wake {
+            let _ = nix::unistd::write(wake, &[0]);
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.state().closed
+    }
+}
+
+
+/// Reader thread of an [`EventHub`], returned by [`EventHub::spawn`].
+///
+/// Dropping the handle closes the hub and waits for the thread to exit.
+#[derive(Debug)]
+pub struct HubThread {
+    hub: EventHub,
+    thread: Option<JoinHandle<std::io::Result<()>>>,
+}
+
+impl HubThread {
+    pub fn hub(&self) -> &EventHub {
+        &self.hub
+    }
+
+    pub fn subscribe(&self) -> Subscriber {
+        self.hub.subscribe()
+    }
+
+    /// Wait for the thread to exit, i.e. for the hub to be closed.
+    ///
+    /// Returns the error reading events has failed with, if any.
+    pub fn join(mut self) -> std::io::Result<()> {
+        let thread = self.thread.take().unwrap();
+
+        match thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl Drop for HubThread {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.hub.close();
+            let _ = thread.join();
+        }
+    }
+}
+
+
+/// Receiving end of an [`EventHub`].
+///
+/// Can be used blocking, via [`Subscriber::recv`] or as [`Iterator`], and
+/// asynchronously as [`Stream`].
+#[derive(Debug)]
+pub struct Subscriber {
+    shared: Arc<Shared>,
+    id: usize,
+}
+
+impl Subscriber {
+    /// Receive the next event, returning `None` once the hub has been closed.
+    pub fn recv(&self) -> Option<Event> {
+        let mut state = self.shared.state();
+
+        loop {
+            match self.pop(&mut state) {
+                Ok(event) => return Some(event),
+                Err(TryRecvError::Closed) => return None,
+                Err(TryRecvError::Empty) => state = self.shared.changed.wait(state).unwrap(),
+            }
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<Event, TryRecvError> {
+        self.pop(&mut self.shared.state())
+    }
+
+    /// Number of events dropped for this subscriber due to lagging behind.
+    pub fn lagged(&self) -> u64 {
+        self.shared.state().subscribers[&self.id].lagged
+    }
+
+    fn pop(&self, state: &mut State) -> Result<Event, TryRecvError> {
+        let closed = state.closed;
+        let queue = state.subscribers.get_mut(&self.id).unwrap();
+
+        match queue.events.pop_front() {
+            Some(event) => {
+                self.shared.notify_publishers(state);
+                Ok(event)
+            },
+            None if closed => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl Drop for Subscriber {
+    fn drop(&mut self) {
+        let mut state = self.shared.state();
+
+        state.subscribers.remove(&self.id);
+        self.shared.notify(&mut state);
+    }
+}
+
+impl Iterator for Subscriber {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        self.recv()
+    }
+}
+
+impl Stream for Subscriber {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Event>> {
+        let mut state = self.shared.state();
+
+        match self.pop(&mut state) {
+            Ok(event) => Poll::Ready(Some(event)),
+            Err(TryRecvError::Closed) => Poll::Ready(None),
+            Err(TryRecvError::Empty) => {
+                state.subscribers.get_mut(&self.id).unwrap().waker = Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
@@ -1,7 +1,6 @@
 use std::convert::TryFrom;
 use std::fs::File;
-use std::io::Read;
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsFd, AsRawFd};
 use std::path::Path;
 
 use futures::io::AsyncRead;
@@ -10,20 +9,76 @@ use tracing::trace;
 
 pub mod uapi;
 
+pub mod backend;
+pub use backend::DtxBackend;
+
+pub mod capture;
+
 pub mod event;
-pub use event::{Event, EventStream, AsyncEventStream};
+pub use event::{Event, EventStream, AsyncEventStream, OwnedEventStream, OwnedAsyncEventStream};
+pub use event::{DecodeMode, EventDecoder, EventFilterExt, ResyncedEvent, TimestampedEvent};
+
+pub mod heartbeat;
+pub use heartbeat::{Heartbeat, HeartbeatThread};
+
+pub mod hub;
+pub use hub::{EventHub, HubThread, LagPolicy, Subscriber};
+
+pub mod lock;
+pub use lock::{LatchGuard, SharedLatch, SharedLatchGuard};
+
+#[cfg(feature = "mio")]
+mod poll;
+
+pub mod session;
+pub use session::{DetachSession, SessionAction, SessionError, SessionState};
+
+pub mod sim;
+
+pub mod state;
+pub use state::{DeviceState, StateChange};
+
+#[cfg(feature = "testing")]
+pub mod testing;
 
 
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("I/O error")]
     IoError { #[from] source: std::io::Error },
 
     #[error("Kernel API/protocol failure")]
     ProtocolError { #[from] source: ProtocolError },
+
+    #[error("Communication with embedded controller failed")]
+    CommunicationError { source: std::io::Error },
+
+    #[error("Device has been shut down")]
+    DeviceShutdown { source: std::io::Error },
+
+    #[error("Permission denied")]
+    PermissionDenied { source: std::io::Error },
+
+    #[error("Operation not supported by device")]
+    NotSupported { source: std::io::Error },
+}
+
+impl Error {
+    pub fn io_error(&self) -> Option<&std::io::Error> {
+        match self {
+            Error::IoError { source }
+                | Error::CommunicationError { source }
+                | Error::DeviceShutdown { source }
+                | Error::PermissionDenied { source }
+                | Error::NotSupported { source } => Some(source),
+            Error::ProtocolError { .. } => None,
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ProtocolError {
     #[error("Invalid value for base state: {0:#04x}")]
     InvalidBaseState(u16),
@@ -36,9 +91,20 @@ pub enum ProtocolError {
 
     #[error("Invalid value for cancel reason: {0:#04x}")]
     InvalidCancelReason(u16),
+
+    #[error("Unknown event code: {0:#04x}")]
+    UnknownEvent(u16),
+
+    #[error("Invalid payload for event code: {0:#04x}")]
+    InvalidEventPayload(u16),
+
+    #[error("Event payload too large: {0} bytes")]
+    PayloadTooLarge(u16),
 }
 
 #[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum RuntimeError {
     #[error("Detachment preconditions not fulfilled")]
     NotFeasible,
@@ -50,7 +116,19 @@ pub enum RuntimeError {
     Unknown(u8),
 }
 
+impl From<RuntimeError> for u16 {
+    fn from(value: RuntimeError) -> Self {
+        match value {
+            RuntimeError::NotFeasible => uapi::SDTX_DETACH_NOT_FEASIBLE,
+            RuntimeError::Timeout     => uapi::SDTX_DETACH_TIMEOUT,
+            RuntimeError::Unknown(v)  => uapi::SDTX_CATEGORY_RUNTIME_ERROR | v as u16,
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum HardwareError {
     #[error("Failed to open latch")]
     FailedToOpen,
@@ -65,8 +143,27 @@ pub enum HardwareError {
     Unknown(u8),
 }
 
+impl From<HardwareError> for u16 {
+    fn from(value: HardwareError) -> Self {
+        match value {
+            HardwareError::FailedToOpen       => uapi::SDTX_ERR_FAILED_TO_OPEN,
+            HardwareError::FailedToRemainOpen => uapi::SDTX_ERR_FAILED_TO_REMAIN_OPEN,
+            HardwareError::FailedToClose      => uapi::SDTX_ERR_FAILED_TO_CLOSE,
+            HardwareError::Unknown(v)         => uapi::SDTX_CATEGORY_HARDWARE_ERROR | v as u16,
+        }
+    }
+}
+
+/// Whether an error value only consists of category and error code, i.e. can
+/// be represented as [`RuntimeError`] or [`HardwareError`] without loss.
+pub(crate) fn is_plain_error(value: u16) -> bool {
+    value & !(uapi::SDTX_CATEGORY_MASK | 0x00ff) == 0
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum DeviceMode {
     Tablet,
     Laptop,
@@ -98,8 +195,20 @@ impl TryFrom<u16> for DeviceMode {
     }
 }
 
+impl From<DeviceMode> for u16 {
+    fn from(value: DeviceMode) -> Self {
+        match value {
+            DeviceMode::Tablet => uapi::SDTX_DEVICE_MODE_TABLET,
+            DeviceMode::Laptop => uapi::SDTX_DEVICE_MODE_LAPTOP,
+            DeviceMode::Studio => uapi::SDTX_DEVICE_MODE_STUDIO,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum LatchStatus {
     Closed,
     Opened,
@@ -127,7 +236,8 @@ impl TryFrom<u16> for LatchStatus {
                 SDTX_ERR_FAILED_TO_OPEN        => Ok(Self::Error(HardwareError::FailedToOpen)),
                 SDTX_ERR_FAILED_TO_REMAIN_OPEN => Ok(Self::Error(HardwareError::FailedToRemainOpen)),
                 SDTX_ERR_FAILED_TO_CLOSE       => Ok(Self::Error(HardwareError::FailedToClose)),
-                x                              => Ok(Self::Error(HardwareError::Unknown(x as u8))),
+                x if is_plain_error(x)         => Ok(Self::Error(HardwareError::Unknown(x as u8))),
+                _ => Err(ProtocolError::InvalidLatchStatus(value)),
             },
             SDTX_CATEGORY_STATUS => match value {
                 SDTX_LATCH_CLOSED              => Ok(Self::Closed),
@@ -139,8 +249,20 @@ impl TryFrom<u16> for LatchStatus {
     }
 }
 
+impl From<LatchStatus> for u16 {
+    fn from(value: LatchStatus) -> Self {
+        match value {
+            LatchStatus::Closed     => uapi::SDTX_LATCH_CLOSED,
+            LatchStatus::Opened     => uapi::SDTX_LATCH_OPENED,
+            LatchStatus::Error(err) => err.into(),
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum BaseState {
     Detached,
     Attached,
@@ -172,8 +294,20 @@ impl TryFrom<u16> for BaseState {
     }
 }
 
+impl From<BaseState> for u16 {
+    fn from(value: BaseState) -> Self {
+        match value {
+            BaseState::Detached    => uapi::SDTX_BASE_DETACHED,
+            BaseState::Attached    => uapi::SDTX_BASE_ATTACHED,
+            BaseState::NotFeasible => uapi::SDTX_DETACH_NOT_FEASIBLE,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum DeviceType {
     Hid,
     Ssh,
@@ -200,8 +334,27 @@ impl From<u16> for DeviceType {
     }
 }
 
+impl From<DeviceType> for u16 {
+    fn from(value: DeviceType) -> Self {
+        match value {
+            DeviceType::Hid        => uapi::SDTX_DEVICE_TYPE_HID,
+            DeviceType::Ssh        => uapi::SDTX_DEVICE_TYPE_SSH,
+            DeviceType::Unknown(v) => ((v as u16) << 8) & uapi::SDTX_DEVICE_TYPE_MASK,
+        }
+    }
+}
+
+impl DeviceType {
+    /// Combine type and base ID into the raw `base_id` value used by the
+    /// kernel. Only the low four bits of an unknown device type are used.
+    pub fn base_id(self, id: u8) -> u16 {
+        u16::from(self) | id as u16
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BaseInfo {
     pub state: BaseState,
     pub device_type: DeviceType,
@@ -220,13 +373,42 @@ impl TryFrom<uapi::BaseInfo> for BaseInfo {
     }
 }
 
+impl From<BaseInfo> for uapi::BaseInfo {
+    fn from(value: BaseInfo) -> Self {
+        uapi::BaseInfo {
+            state: value.state.into(),
+            base_id: value.device_type.base_id(value.id),
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum CancelReason {
     Runtime(RuntimeError),
     Hardware(HardwareError),
 }
 
+impl std::fmt::Display for CancelReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CancelReason::Runtime(err)  => write!(f, "{err}"),
+            CancelReason::Hardware(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<CancelReason> for u16 {
+    fn from(value: CancelReason) -> Self {
+        match value {
+            CancelReason::Runtime(err)  => err.into(),
+            CancelReason::Hardware(err) => err.into(),
+        }
+    }
+}
+
 
 pub const DEFAULT_DEVICE_FILE_PATH: &str = "/dev/surface/dtx";
 
@@ -236,6 +418,7 @@ pub fn connect() -> std::io::Result<Device<File>> {
 
 
 #[derive(Debug)]
+#[repr(transparent)]
 pub struct Device<F> {
     file: F,
 }
@@ -245,6 +428,11 @@ impl<F> Device<F> {
         Device { file }
     }
 
+    pub(crate) fn from_backend(file: &F) -> &Self {
+        // SAFETY: Device is a transparent wrapper around F.
+        unsafe { &*(file as *const F as *const Self) }
+    }
+
     pub fn file(&self) -> &F {
         &self.file
     }
@@ -252,6 +440,10 @@ impl<F> Device<F> {
     pub fn file_mut(&mut self) -> &mut F {
         &mut self.file
     }
+
+    pub fn into_file(self) -> F {
+        self.file
+    }
 }
 
 impl Device<File> {
@@ -264,176 +456,195 @@ impl Device<File> {
             file: File::open(path)?,
         })
     }
+
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(Device {
+            file: self.file.try_clone()?,
+        })
+    }
 }
 
-impl<F: AsRawFd> Device<F> {
-    pub fn latch_lock(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_latch_lock(self.file.as_raw_fd()) }
-            .map(|_| ());
+impl<F: AsFd> Device<F> {
+    /// Switch the device file to non-blocking mode, e.g. for use with
+    /// [`EventStream::try_read_events`].
+    pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+        use nix::fcntl::{fcntl, FcntlArg, OFlag};
+
+        let fd = self.file.as_fd().as_raw_fd();
+        let flags = fcntl(fd, FcntlArg::F_GETFL)?;
+        let mut flags = OFlag::from_bits_truncate(flags);
+        flags.set(OFlag::O_NONBLOCK, nonblocking);
+        fcntl(fd, FcntlArg::F_SETFL(flags))?;
+
+        Ok(())
+    }
+}
+
+impl<F: DtxBackend> Device<F> {
+    pub fn latch_lock(&self) -> Result<(), Error> {
+        let result = self.file.latch_lock();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_latch_lock"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_latch_lock"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
     }
 
-    pub fn latch_unlock(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_latch_unlock(self.file.as_raw_fd()) }
-            .map(|_| ());
+    pub fn latch_unlock(&self) -> Result<(), Error> {
+        let result = self.file.latch_unlock();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_latch_unlock"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_latch_unlock"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
+    }
+
+    /// Lock the latch until the returned guard is dropped.
+    ///
+    /// Guards do not nest: dropping any guard unlocks the latch, even if other
+    /// guards still exist. Use a [`SharedLatch`] to hold the lock from multiple
+    /// places.
+    pub fn lock_guard(&self) -> Result<LatchGuard<'_, F>, Error> {
+        LatchGuard::new(self)
     }
 
-    pub fn latch_request(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_latch_request(self.file.as_raw_fd()) }
-            .map(|_| ());
+    pub fn latch_request(&self) -> Result<(), Error> {
+        let result = self.file.latch_request();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_latch_request"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_latch_request"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
     }
 
-    pub fn latch_confirm(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_latch_confirm(self.file.as_raw_fd()) }
-            .map(|_| ());
+    pub fn latch_confirm(&self) -> Result<(), Error> {
+        let result = self.file.latch_confirm();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_latch_confirm"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_latch_confirm"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
     }
 
-    pub fn latch_heartbeat(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_latch_heartbeat(self.file.as_raw_fd()) }
-            .map(|_| ());
+    pub fn latch_heartbeat(&self) -> Result<(), Error> {
+        let result = self.file.latch_heartbeat();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_latch_heartbeat"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_latch_heartbeat"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
     }
 
-    pub fn latch_cancel(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_latch_cancel(self.file.as_raw_fd()) }
-            .map(|_| ());
+    pub fn latch_cancel(&self) -> Result<(), Error> {
+        let result = self.file.latch_cancel();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_latch_cancel"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_latch_cancel"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
     }
 
     pub fn get_base_info(&self) -> Result<BaseInfo, Error> {
-        let mut info = uapi::BaseInfo {
-            state: 0,
-            base_id: 0,
-        };
-
-        let result = unsafe { uapi::dtx_get_base_info(self.file.as_raw_fd(), &mut info as *mut uapi::BaseInfo) }
-            .map_err(nix_to_dtx_err);
-
-        let state = info.state;
-        let base_id = info.base_id;
+        let result = self.file.get_base_info();
 
         match result {
-            Ok(_) => {
+            Ok(info) => {
+                let state = info.state;
+                let base_id = info.base_id;
+
                 trace!(target: "sdtx::ioctl", state, base_id, "dtx_get_base_info");
                 Ok(BaseInfo::try_from(info)?)
             },
             Err(e) => {
                 trace!(target: "sdtx::ioctl", error=%e, "dtx_get_base_info");
-                Err(e)
+                Err(ioctl_error(e))
             }
         }
     }
 
     pub fn get_device_mode(&self) -> Result<DeviceMode, Error> {
-        let mut mode: u16 = 0;
-
-        let result = unsafe { uapi::dtx_get_device_mode(self.file.as_raw_fd(), &mut mode as *mut u16) }
-            .map_err(nix_to_dtx_err);
+        let result = self.file.get_device_mode();
 
         match result {
-            Ok(_) => {
+            Ok(mode) => {
                 trace!(target: "sdtx::ioctl", mode, "dtx_get_device_mode");
                 Ok(DeviceMode::try_from(mode)?)
             },
             Err(e) => {
                 trace!(target: "sdtx::ioctl", error=%e, "dtx_get_device_mode");
-                Err(e)
+                Err(ioctl_error(e))
             }
         }
     }
 
     pub fn get_latch_status(&self) -> Result<LatchStatus, Error> {
-        let mut status: u16 = 0;
-
-        let result = unsafe { uapi::dtx_get_latch_status(self.file.as_raw_fd(), &mut status as *mut u16) }
-            .map_err(nix_to_dtx_err);
+        let result = self.file.get_latch_status();
 
         match result {
-            Ok(_) => {
+            Ok(status) => {
                 trace!(target: "sdtx::ioctl", status, "dtx_get_latch_status");
                 Ok(LatchStatus::try_from(status)?)
             },
             Err(e) => {
                 trace!(target: "sdtx::ioctl", error=%e, "dtx_get_latch_status");
-                Err(e)
+                Err(ioctl_error(e))
             }
         }
     }
 
-    pub fn events_enable(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_events_enable(self.file.as_raw_fd()) }
-            .map(|_| ());
+    pub fn events_enable(&self) -> Result<(), Error> {
+        let result = self.file.events_enable();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_events_enable"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_events_enable"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
     }
 
-    pub fn events_disable(&self) -> std::io::Result<()> {
-        let result = unsafe { uapi::dtx_events_disable(self.file.as_raw_fd()) }
-            .map(|_| ());
+    pub fn events_disable(&self) -> Result<(), Error> {
+        let result = self.file.events_disable();
 
         match result {
             Ok(()) => trace!(target: "sdtx::ioctl", "dtx_events_disable"),
             Err(ref e) => trace!(target: "sdtx::ioctl", error=%e, "dtx_events_disable"),
         }
 
-        Ok(result?)
+        result.map_err(ioctl_error)
     }
 }
 
-impl<F: AsRawFd + Read> Device<F> {
-    pub fn events(&mut self) -> std::io::Result<EventStream<F>> {
+impl<F: DtxBackend> Device<F> {
+    pub fn events(&mut self) -> Result<EventStream<'_, F>, Error> {
         EventStream::from_device(self)
     }
+
+    pub fn into_events(self) -> Result<OwnedEventStream<F>, Error> {
+        OwnedEventStream::from_device(self)
+    }
 }
 
-impl<F: AsRawFd + AsyncRead + Unpin> Device<F> {
-    pub fn events_async(&mut self) -> std::io::Result<AsyncEventStream<F>> {
+impl<F: DtxBackend + AsyncRead + Unpin> Device<F> {
+    pub fn events_async(&mut self) -> Result<AsyncEventStream<'_, F>, Error> {
         AsyncEventStream::from_device(self)
     }
+
+    pub fn into_events_async(self) -> Result<OwnedAsyncEventStream<F>, Error> {
+        OwnedAsyncEventStream::from_device(self)
+    }
 }
 
 impl<F> From<F> for Device<F> {
@@ -443,6 +654,53 @@ impl<F> From<F> for Device<F> {
 }
 
 
-fn nix_to_dtx_err(err: nix::Error) -> Error {
-    std::io::Error::from(err).into()
+fn ioctl_error(err: std::io::Error) -> Error {
+    use nix::errno::Errno;
+
+    match err.raw_os_error().map(Errno::from_raw) {
+        Some(Errno::ENODEV)    | Some(Errno::ESHUTDOWN) => Error::DeviceShutdown { source: err },
+        Some(Errno::EACCES)    | Some(Errno::EPERM)     => Error::PermissionDenied { source: err },
+        Some(Errno::EREMOTEIO) | Some(Errno::ETIMEDOUT) => Error::CommunicationError { source: err },
+        Some(Errno::ENOTTY) | Some(Errno::EINVAL) | Some(Errno::EOPNOTSUPP) => {
+            Error::NotSupported { source: err }
+        },
+        _ => Error::IoError { source: err },
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use nix::errno::Errno;
+
+    use super::{ioctl_error, Error};
+
+    fn map(errno: Errno) -> Error {
+        ioctl_error(std::io::Error::from(errno))
+    }
+
+    #[test]
+    fn ioctl_error_mapping() {
+        assert!(matches!(map(Errno::ENODEV), Error::DeviceShutdown { .. }));
+        assert!(matches!(map(Errno::ESHUTDOWN), Error::DeviceShutdown { .. }));
+        assert!(matches!(map(Errno::EACCES), Error::PermissionDenied { .. }));
+        assert!(matches!(map(Errno::EPERM), Error::PermissionDenied { .. }));
+        assert!(matches!(map(Errno::EREMOTEIO), Error::CommunicationError { .. }));
+        assert!(matches!(map(Errno::ETIMEDOUT), Error::CommunicationError { .. }));
+        assert!(matches!(map(Errno::ENOTTY), Error::NotSupported { .. }));
+        assert!(matches!(map(Errno::EINVAL), Error::NotSupported { .. }));
+        assert!(matches!(map(Errno::EOPNOTSUPP), Error::NotSupported { .. }));
+        assert!(matches!(map(Errno::EAGAIN), Error::IoError { .. }));
+        assert!(matches!(map(Errno::EBUSY), Error::IoError { .. }));
+        assert!(matches!(map(Errno::EIO), Error::IoError { .. }));
+    }
+
+    #[test]
+    fn ioctl_error_keeps_source() {
+        let err = map(Errno::EREMOTEIO);
+        assert_eq!(err.io_error().and_then(|e| e.raw_os_error()), Some(Errno::EREMOTEIO as i32));
+
+        let err = ioctl_error(std::io::ErrorKind::Other.into());
+        assert!(matches!(err, Error::IoError { .. }));
+    }
 }
@@ -0,0 +1,113 @@
+use std::sync::{Arc, Mutex};
+
+use tracing::warn;
+
+use crate::{Device, DtxBackend, Error};
+
+
+/// Keeps the latch locked, unlocking it again when dropped.
+///
+/// The device does not count locks, so a guard unlocks the latch regardless
+/// of any other guards. See [`SharedLatch`] for a lock with multiple holders.
+/// Failing to unlock the latch on drop is logged as warning.
+#[derive(Debug)]
+pub struct LatchGuard<'a, F: DtxBackend> {
+    device: Option<&'a Device<F>>,
+}
+
+impl<'a, F: DtxBackend> LatchGuard<'a, F> {
+    pub(crate) fn new(device: &'a Device<F>) -> Result<Self, Error> {
+        device.latch_lock()?;
+
+        Ok(LatchGuard { device: Some(device) })
+    }
+
+    pub fn device(&self) -> &'a Device<F> {
+        self.device.unwrap()
+    }
+
+    pub fn unlock(mut self) -> Result<(), Error> {
+        self.device.take().unwrap().latch_unlock()
+    }
+}
+
+impl<F: DtxBackend> Drop for LatchGuard<'_, F> {
+    fn drop(&mut self) {
+        if let Some(device) = self.device.take() {
+            unlock(device);
+        }
+    }
+}
+
+
+#[derive(Debug)]
+struct SharedInner<F> {
+    device: Arc<Device<F>>,
+    holders: Mutex<usize>,
+}
+
+/// Reference-counted latch lock shared by multiple holders.
+///
+/// The latch is locked when the first guard is acquired and unlocked once the
+/// last guard has been dropped.
+#[derive(Debug)]
+pub struct SharedLatch<F> {
+    inner: Arc<SharedInner<F>>,
+}
+
+impl<F: DtxBackend> SharedLatch<F> {
+    pub fn new(device: Arc<Device<F>>) -> Self {
+        let inner = SharedInner { device, holders: Mutex::new(0) };
+
+        SharedLatch { inner: Arc::new(inner) }
+    }
+
+    pub fn device(&self) -> &Arc<Device<F>> {
+        &self.inner.device
+    }
+
+    pub fn holders(&self) -> usize {
+        *self.inner.holders.lock().unwrap()
+    }
+
+    pub fn acquire(&self) -> Result<SharedLatchGuard<F>, Error> {
+        let mut holders = self.inner.holders.lock().unwrap();
+
+        if *holders == 0 {
+            self.inner.device.latch_lock()?;
+        }
+        *holders += 1;
+
+        Ok(SharedLatchGuard { inner: self.inner.clone() })
+    }
+}
+
+impl<F> Clone for SharedLatch<F> {
+    fn clone(&self) -> Self {
+        SharedLatch { inner: self.inner.clone() }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct SharedLatchGuard<F: DtxBackend> {
+    inner: Arc<SharedInner<F>>,
+}
+
+impl<F: DtxBackend> Drop for SharedLatchGuard<F> {
+    fn drop(&mut self) {
+        let mut holders = self.inner.holders.lock().unwrap();
+
+        *holders -= 1;
+        if *holders == 0 {
+            unlock(&self.inner.device);
+        }
+    }
+}
+
+
+fn unlock<F: DtxBackend>(device: &Device<F>) {
+    if let Err(err) = device.latch_unlock() {
+        warn!(target: "sdtx::ioctl", error=%err, "failed to unlock latch on drop");
+    }
+}
@@ -0,0 +1,43 @@
+use std::os::unix::io::AsRawFd;
+
+use mio::event::Source;
+use mio::unix::SourceFd;
+use mio::{Interest, Registry, Token};
+
+use crate::{Device, DtxBackend, EventStream, OwnedEventStream};
+
+
+/// Implement [`Source`] by registering the file descriptor of the device
+/// returned by the given expression.
+macro_rules! device_fd_source {
+    ($this:ident => $device:expr) => {
+        fn register(&mut $this, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+            SourceFd(&$device.file().as_raw_fd()).register(registry, token, interests)
+        }
+
+        fn reregister(&mut $this, registry: &Registry, token: Token, interests: Interest) -> std::io::Result<()> {
+            SourceFd(&$device.file().as_raw_fd()).reregister(registry, token, interests)
+        }
+
+        fn deregister(&mut $this, registry: &Registry) -> std::io::Result<()> {
+            SourceFd(&$device.file().as_raw_fd()).deregister(registry)
+        }
+    };
+}
+
+
+/// Allows registering the device file with a [`mio::Poll`].
+///
+/// Once readable, events can be drained via
+/// [`EventStream::try_read_events`], with the device in non-blocking mode.
+impl<F: AsRawFd> Source for Device<F> {
+    device_fd_source!(self => self);
+}
+
+impl<F: DtxBackend + AsRawFd> Source for EventStream<'_, F> {
+    device_fd_source!(self => self.device());
+}
+
+impl<F: DtxBackend + AsRawFd> Source for OwnedEventStream<F> {
+    device_fd_source!(self => self.device());
+}
@@ -0,0 +1,203 @@
+use std::convert::TryFrom;
+
+use tracing::debug;
+
+use crate::event;
+use crate::{CancelReason, Device, DtxBackend, Error, Event, ProtocolError};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    Idle,
+    Requested,
+    AwaitingConfirm,
+    Unlocked,
+    Detached,
+    Cancelled(CancelReason),
+    Aborted,
+}
+
+impl SessionState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Detached | Self::Cancelled(_) | Self::Aborted)
+    }
+}
+
+impl std::fmt::Display for SessionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SessionState::Idle            => write!(f, "Idle"),
+            SessionState::Requested       => write!(f, "Requested"),
+            SessionState::AwaitingConfirm => write!(f, "AwaitingConfirm"),
+            SessionState::Unlocked        => write!(f, "Unlocked"),
+            SessionState::Detached        => write!(f, "Detached"),
+            SessionState::Cancelled(reason) => write!(f, "Cancelled: {reason}"),
+            SessionState::Aborted         => write!(f, "Aborted"),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionAction {
+    Request,
+    Confirm,
+    Heartbeat,
+    Cancel,
+}
+
+impl std::fmt::Display for SessionAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            SessionAction::Request   => "request",
+            SessionAction::Confirm   => "confirm",
+            SessionAction::Heartbeat => "heartbeat",
+            SessionAction::Cancel    => "cancel",
+        };
+
+        write!(f, "{name}")
+    }
+}
+
+
+#[derive(thiserror::Error, Debug)]
+pub enum SessionError {
+    #[error("Cannot {action} detachment in state {state}")]
+    InvalidTransition { action: SessionAction, state: SessionState },
+
+    #[error("Device operation failed")]
+    DeviceError { #[from] source: Error },
+}
+
+impl From<ProtocolError> for SessionError {
+    fn from(source: ProtocolError) -> Self {
+        Error::from(source).into()
+    }
+}
+
+
+/// State machine driving a single detachment procedure.
+///
+/// Events received from the device are fed in via [`DetachSession::handle`],
+/// commands are only sent to the device if they are valid in the current
+/// state.
+#[derive(Debug, Clone)]
+pub struct DetachSession {
+    state: SessionState,
+}
+
+impl DetachSession {
+    pub fn new() -> Self {
+        DetachSession { state: SessionState::Idle }
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    pub fn request<F: DtxBackend>(&mut self, device: &Device<F>) -> Result<(), SessionError> {
+        self.check(SessionAction::Request, &[SessionState::Idle])?;
+
+        device.latch_request()?;
+        self.transition(SessionState::Requested);
+
+        Ok(())
+    }
+
+    pub fn confirm<F: DtxBackend>(&mut self, device: &Device<F>) -> Result<(), SessionError> {
+        self.check(SessionAction::Confirm, &[SessionState::AwaitingConfirm])?;
+
+        device.latch_confirm()?;
+        Ok(())
+    }
+
+    pub fn heartbeat<F: DtxBackend>(&mut self, device: &Device<F>) -> Result<(), SessionError> {
+        use SessionState::*;
+
+        self.check(SessionAction::Heartbeat, &[Requested, AwaitingConfirm, Unlocked])?;
+
+        device.latch_heartbeat()?;
+        Ok(())
+    }
+
+    pub fn cancel<F: DtxBackend>(&mut self, device: &Device<F>) -> Result<(), SessionError> {
+        use SessionState::*;
+
+        self.check(SessionAction::Cancel, &[Requested, AwaitingConfirm, Unlocked])?;
+
+        device.latch_cancel()?;
+        self.transition(SessionState::Aborted);
+
+        Ok(())
+    }
+
+    /// Update the state with an event received from the device.
+    ///
+    /// A cancellation with a reason that cannot be represented as
+    /// [`CancelReason`] still ends the session as [`SessionState::Aborted`],
+    /// the reason is then returned as [`ProtocolError::InvalidCancelReason`].
+    pub fn handle(&mut self, event: &Event) -> Result<SessionState, SessionError> {
+        use SessionState::*;
+
+        if self.state.is_finished() {
+            return Ok(self.state);
+        }
+
+        let next = match (self.state, event) {
+            (Idle, Event::Request) | (Requested, Event::Request) => {
+                AwaitingConfirm
+            },
+            (AwaitingConfirm, Event::Request) | (Unlocked, Event::Request) => {
+                Aborted
+            },
+            (Requested, Event::Cancel { reason })
+                    | (AwaitingConfirm, Event::Cancel { reason })
+                    | (Unlocked, Event::Cancel { reason }) => {
+                match CancelReason::try_from(*reason) {
+                    Ok(reason) => Cancelled(reason),
+                    Err(err) => {
+                        self.transition(Aborted);
+                        return Err(err.into());
+                    },
+                }
+            },
+            (AwaitingConfirm, Event::LatchStatus { status: event::LatchStatus::Opened }) => {
+                Unlocked
+            },
+            (Unlocked, Event::LatchStatus { status: event::LatchStatus::Closed }) => {
+                Aborted
+            },
+            (_, Event::LatchStatus { status: event::LatchStatus::Error(err) }) if self.state != Idle => {
+                Cancelled(CancelReason::Hardware(*err))
+            },
+            (Unlocked, Event::BaseConnection { state: event::BaseState::Detached, .. }) => {
+                Detached
+            },
+            (state, _) => state,
+        };
+
+        self.transition(next);
+        Ok(self.state)
+    }
+
+    fn check(&self, action: SessionAction, valid: &[SessionState]) -> Result<(), SessionError> {
+        if valid.contains(&self.state) {
+            Ok(())
+        } else {
+            Err(SessionError::InvalidTransition { action, state: self.state })
+        }
+    }
+
+    fn transition(&mut self, next: SessionState) {
+        if self.state != next {
+            debug!(target: "sdtx::session", from=%self.state, to=%next, "detachment state changed");
+            self.state = next;
+        }
+    }
+}
+
+impl Default for DetachSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
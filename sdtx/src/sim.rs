@@ -0,0 +1,683 @@
+use std::collections::VecDeque;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use futures::io::AsyncRead;
+
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+
+use tracing::trace;
+
+use crate::uapi;
+use crate::{BaseInfo, BaseState, CancelReason, Device, DeviceMode, DeviceType, DtxBackend, Event};
+use crate::{HardwareError, LatchStatus, RuntimeError};
+
+
+pub const DEFAULT_DETACH_TIMEOUT: Duration = Duration::from_secs(5);
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Detach {
+    Idle,
+    Pending { elapsed: Duration },
+    Opened { elapsed: Duration },
+}
+
+/// Pipe standing in for the file descriptor of a client.
+///
+/// Holds a single byte while the client is readable, i.e. while events are
+/// queued or the device has been shut down, so that handles can be polled
+/// like the device file.
+#[derive(Debug)]
+struct Readiness {
+    rx: OwnedFd,
+    tx: OwnedFd,
+}
+
+impl Readiness {
+    fn new() -> std::io::Result<Self> {
+        let (rx, tx) = nix::unistd::pipe2(OFlag::O_CLOEXEC)?;
+        fcntl(tx.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+
+        Ok(Readiness { rx, tx })
+    }
+
+    fn set(&self, ready: bool) {
+        // Errors are ignored: the pipe only ever holds a single byte and the
+        // byte to read is known to be present.
+        if ready {
+            let _ = nix::unistd::write(&self.tx, &[0]);
+        } else {
+            let _ = nix::unistd::read(self.rx.as_raw_fd(), &mut [0]);
+        }
+    }
+
+    fn is_nonblocking(&self) -> std::io::Result<bool> {
+        let flags = fcntl(self.rx.as_raw_fd(), FcntlArg::F_GETFL)?;
+        Ok(OFlag::from_bits_truncate(flags).contains(OFlag::O_NONBLOCK))
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    id: usize,
+    handles: usize,
+    enabled: bool,
+    queue: VecDeque<u8>,
+    waker: Option<Waker>,
+    readiness: Arc<Readiness>,
+    ready: bool,
+}
+
+impl Client {
+    fn update_readiness(&mut self, shutdown: bool) {
+        let ready = shutdown || !self.queue.is_empty();
+
+        if ready != self.ready {
+            self.readiness.set(ready);
+            self.ready = ready;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    base: BaseInfo,
+    mode: DeviceMode,
+    latch: LatchStatus,
+    locked: bool,
+    detach: Detach,
+    timeout: Duration,
+    fault: Option<HardwareError>,
+    heartbeats: u64,
+    shutdown: bool,
+    clients: Vec<Client>,
+    next_client: usize,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    readable: Condvar,
+}
+
+
+/// In-process model of the EC side of the DTX detachment protocol.
+///
+/// Handles returned by [`Simulator::open`] behave like separate open files of
+/// the DTX device and can be used as backend for a [`Device`]. The simulator
+/// itself is used to script user and hardware actions. Time is simulated and
+/// only advances via [`Simulator::advance`].
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    shared: Arc<Shared>,
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        let base = BaseInfo {
+            state: BaseState::Attached,
+            device_type: DeviceType::Ssh,
+            id: 0,
+        };
+
+        let state = State {
+            base,
+            mode: DeviceMode::Laptop,
+            latch: LatchStatus::Closed,
+            locked: false,
+            detach: Detach::Idle,
+            timeout: DEFAULT_DETACH_TIMEOUT,
+            fault: None,
+            heartbeats: 0,
+            shutdown: false,
+            clients: Vec::new(),
+            next_client: 0,
+        };
+
+        let shared = Shared {
+            state: Mutex::new(state),
+            readable: Condvar::new(),
+        };
+
+        Simulator { shared: Arc::new(shared) }
+    }
+
+    /// Open a new handle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pipe backing the file descriptor of the handle cannot
+    /// be created.
+    pub fn open(&self) -> SimDevice {
+        let readiness = Arc::new(Readiness::new().expect("failed to create simulator pipe"));
+        let mut state = self.state();
+
+        let id = state.next_client;
+        state.next_client += 1;
+
+        let mut client = Client {
+            id,
+            handles: 1,
+            enabled: false,
+            queue: VecDeque::new(),
+            waker: None,
+            readiness: readiness.clone(),
+            ready: false,
+        };
+        client.update_readiness(state.shutdown);
+        state.clients.push(client);
+
+        SimDevice { shared: self.shared.clone(), id, readiness }
+    }
+
+    pub fn device(&self) -> Device<SimDevice> {
+        Device::from(self.open())
+    }
+
+    pub fn base_info(&self) -> BaseInfo {
+        self.state().base
+    }
+
+    pub fn device_mode(&self) -> DeviceMode {
+        self.state().mode
+    }
+
+    pub fn latch_status(&self) -> LatchStatus {
+        self.state().latch
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state().locked
+    }
+
+    pub fn is_detach_in_progress(&self) -> bool {
+        self.state().detach != Detach::Idle
+    }
+
+    /// Number of heartbeats received so far.
+    pub fn heartbeats(&self) -> u64 {
+        self.state().heartbeats
+    }
+
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.state().timeout = timeout;
+    }
+
+    pub fn press_detach_button(&self) {
+        trace!(target: "sdtx::sim", "detach button pressed");
+        self.update(State::request);
+    }
+
+    pub fn remove_base(&self) {
+        trace!(target: "sdtx::sim", "base removed");
+        self.update(State::remove_base);
+    }
+
+    pub fn attach_base(&self, device_type: DeviceType, id: u8) {
+        trace!(target: "sdtx::sim", %device_type, id, "base attached");
+        self.update(|s| s.attach_base(device_type, id));
+    }
+
+    pub fn set_low_battery(&self, low: bool) {
+        trace!(target: "sdtx::sim", low, "clipboard battery state changed");
+        self.update(|s| s.set_low_battery(low));
+    }
+
+    pub fn set_device_mode(&self, mode: DeviceMode) {
+        trace!(target: "sdtx::sim", %mode, "device mode changed");
+        self.update(|s| s.set_mode(mode));
+    }
+
+    /// Let the next latch operation fail with the given error.
+    ///
+    /// `FailedToOpen` and `FailedToRemainOpen` trigger when the latch is
+    /// opened, `FailedToClose` when it closes again.
+    pub fn fail_latch(&self, error: HardwareError) {
+        trace!(target: "sdtx::sim", %error, "latch failure injected");
+        self.state().fault = Some(error);
+    }
+
+    pub fn advance(&self, time: Duration) {
+        self.update(|s| s.advance(time));
+    }
+
+    /// Simulate removal of the DTX device, e.g. due to driver unload.
+    pub fn shutdown(&self) {
+        trace!(target: "sdtx::sim", "device shut down");
+        self.update(|s| s.shutdown = true);
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared.state()
+    }
+
+    fn update<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        self.shared.update(f)
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn update<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        let mut state = self.state();
+        let result = f(&mut state);
+
+        let shutdown = state.shutdown;
+        for client in state.clients.iter_mut() {
+            client.update_readiness(shutdown);
+        }
+
+        for client in state.clients.iter_mut().filter(|c| !c.queue.is_empty()) {
+            if let Some(waker) = client.waker.take() {
+                waker.wake();
+            }
+        }
+
+        if state.shutdown {
+            for waker in state.clients.iter_mut().filter_map(|c| c.waker.take()) {
+                waker.wake();
+            }
+        }
+
+        self.readable.notify_all();
+        result
+    }
+
+    fn ioctl<R>(&self, name: &str, f: impl FnOnce(&mut State) -> R) -> std::io::Result<R> {
+        self.update(|state| {
+            if state.shutdown {
+                return Err(nix::errno::Errno::ENODEV.into());
+            }
+
+            trace!(target: "sdtx::sim", "{}", name);
+            Ok(f(state))
+        })
+    }
+}
+
+
+impl State {
+    fn emit(&mut self, event: Event) {
+        let data = event.to_bytes();
+
+        for client in self.clients.iter_mut().filter(|c| c.enabled) {
+            client.queue.extend(&data);
+        }
+    }
+
+    fn emit_request(&mut self) {
+        self.emit(Event::Request);
+    }
+
+    fn emit_cancel(&mut self, reason: CancelReason) {
+        self.emit(Event::Cancel { reason: reason.into() });
+    }
+
+    fn emit_base(&mut self) {
+        self.emit(Event::BaseConnection {
+            state: self.base.state.into(),
+            device_type: self.base.device_type,
+            id: self.base.id,
+        });
+    }
+
+    fn emit_latch(&mut self) {
+        self.emit(Event::LatchStatus { status: self.latch.into() });
+    }
+
+    fn emit_mode(&mut self) {
+        self.emit(Event::DeviceMode { mode: self.mode.into() });
+    }
+
+    fn request(&mut self) {
+        match self.detach {
+            Detach::Idle => match self.base.state {
+                BaseState::Attached => {
+                    self.detach = Detach::Pending { elapsed: Duration::ZERO };
+                    self.emit_request();
+                },
+                BaseState::NotFeasible => {
+                    self.emit_cancel(CancelReason::Runtime(RuntimeError::NotFeasible));
+                },
+                BaseState::Detached => {},
+            },
+            Detach::Pending { .. } => {
+                self.detach = Detach::Idle;
+                self.emit_request();
+            },
+            Detach::Opened { .. } => {
+                self.emit_request();
+                self.close_latch();
+            },
+        }
+    }
+
+    fn confirm(&mut self) {
+        if let Detach::Pending { .. } = self.detach {
+            self.locked = false;
+            self.open_latch();
+        }
+    }
+
+    fn heartbeat(&mut self) {
+        self.heartbeats += 1;
+
+        match self.detach {
+            Detach::Idle => {},
+            Detach::Pending { .. } => self.detach = Detach::Pending { elapsed: Duration::ZERO },
+            Detach::Opened { .. } => self.detach = Detach::Opened { elapsed: Duration::ZERO },
+        }
+    }
+
+    fn cancel(&mut self) {
+        match self.detach {
+            Detach::Idle => {},
+            Detach::Pending { .. } => {
+                self.detach = Detach::Idle;
+                self.emit_request();
+            },
+            Detach::Opened { .. } => {
+                self.emit_request();
+                self.close_latch();
+            },
+        }
+    }
+
+    fn advance(&mut self, time: Duration) {
+        match self.detach {
+            Detach::Idle => {},
+            Detach::Pending { elapsed } => {
+                let elapsed = elapsed + time;
+
+                if elapsed < self.timeout {
+                    self.detach = Detach::Pending { elapsed };
+                } else if self.locked {
+                    self.detach = Detach::Idle;
+                    self.emit_cancel(CancelReason::Runtime(RuntimeError::Timeout));
+                } else {
+                    self.open_latch();
+                }
+            },
+            Detach::Opened { elapsed } => {
+                let elapsed = elapsed + time;
+
+                if elapsed < self.timeout {
+                    self.detach = Detach::Opened { elapsed };
+                } else {
+                    self.close_latch();
+                }
+            },
+        }
+    }
+
+    fn open_latch(&mut self) {
+        match self.fault {
+            Some(err @ HardwareError::FailedToOpen) => {
+                self.fault = None;
+                self.latch_failure(err);
+            },
+            Some(err @ HardwareError::FailedToRemainOpen) => {
+                self.fault = None;
+                self.latch = LatchStatus::Opened;
+                self.emit_latch();
+                self.latch_failure(err);
+            },
+            _ => {
+                self.detach = Detach::Opened { elapsed: Duration::ZERO };
+                self.latch = LatchStatus::Opened;
+                self.emit_latch();
+            },
+        }
+    }
+
+    fn close_latch(&mut self) {
+        match self.fault {
+            Some(err @ HardwareError::FailedToClose) => {
+                self.fault = None;
+                self.latch_failure(err);
+            },
+            _ => {
+                self.detach = Detach::Idle;
+                self.latch = LatchStatus::Closed;
+                self.emit_latch();
+            },
+        }
+    }
+
+    fn latch_failure(&mut self, err: HardwareError) {
+        self.detach = Detach::Idle;
+        self.latch = LatchStatus::Error(err);
+        self.emit_latch();
+        self.emit_cancel(CancelReason::Hardware(err));
+    }
+
+    fn remove_base(&mut self) {
+        if self.base.state == BaseState::Detached {
+            return;
+        }
+
+        self.base.state = BaseState::Detached;
+        self.emit_base();
+        self.set_mode(DeviceMode::Tablet);
+
+        if let Detach::Opened { .. } = self.detach {
+            self.close_latch();
+        }
+    }
+
+    fn attach_base(&mut self, device_type: DeviceType, id: u8) {
+        self.base = BaseInfo { state: BaseState::Attached, device_type, id };
+        self.emit_base();
+        self.set_mode(DeviceMode::Laptop);
+    }
+
+    fn set_low_battery(&mut self, low: bool) {
+        let state = match (self.base.state, low) {
+            (BaseState::Attached, true) => BaseState::NotFeasible,
+            (BaseState::NotFeasible, false) => BaseState::Attached,
+            _ => return,
+        };
+
+        self.base.state = state;
+        self.emit_base();
+    }
+
+    fn set_mode(&mut self, mode: DeviceMode) {
+        if self.mode != mode {
+            self.mode = mode;
+            self.emit_mode();
+        }
+    }
+}
+
+
+/// Handle to a [`Simulator`], equivalent to an open file of the DTX device.
+///
+/// The file descriptor of the handle becomes readable when events are
+/// available, so it can be polled and switched to non-blocking mode like the
+/// device file. It is not meant to be read from directly.
+#[derive(Debug)]
+pub struct SimDevice {
+    shared: Arc<Shared>,
+    id: usize,
+    readiness: Arc<Readiness>,
+}
+
+impl SimDevice {
+    pub fn simulator(&self) -> Simulator {
+        Simulator { shared: self.shared.clone() }
+    }
+
+    /// Duplicate the handle, equivalent to duplicating the file descriptor.
+    ///
+    /// Both handles share event state, i.e. whether events are enabled and
+    /// the queue of events not yet read.
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        let mut state = self.shared.state();
+        Self::with_client(&mut state, self.id, |c| c.handles += 1);
+
+        Ok(SimDevice { shared: self.shared.clone(), id: self.id, readiness: self.readiness.clone() })
+    }
+
+    fn with_client<R>(state: &mut State, id: usize, f: impl FnOnce(&mut Client) -> R) -> R {
+        let client = state.clients.iter_mut()
+            .find(|c| c.id == id)
+            .expect("simulator client not registered");
+
+        f(client)
+    }
+}
+
+impl Drop for SimDevice {
+    fn drop(&mut self) {
+        let mut state = self.shared.state();
+
+        let handles = Self::with_client(&mut state, self.id, |c| {
+            c.handles -= 1;
+            c.handles
+        });
+
+        if handles == 0 {
+            state.clients.retain(|c| c.id != self.id);
+        }
+    }
+}
+
+impl Device<SimDevice> {
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(Device::from(self.file().try_clone()?))
+    }
+}
+
+impl DtxBackend for SimDevice {
+    fn events_enable(&self) -> std::io::Result<()> {
+        let id = self.id;
+        self.shared.ioctl("dtx_events_enable", |s| Self::with_client(s, id, |c| c.enabled = true))
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        let id = self.id;
+        self.shared.ioctl("dtx_events_disable", |s| Self::with_client(s, id, |c| c.enabled = false))
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        self.shared.ioctl("dtx_latch_lock", |s| s.locked = true)
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        self.shared.ioctl("dtx_latch_unlock", |s| s.locked = false)
+    }
+
+    fn latch_request(&self) -> std::io::Result<()> {
+        self.shared.ioctl("dtx_latch_request", State::request)
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        self.shared.ioctl("dtx_latch_confirm", State::confirm)
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        self.shared.ioctl("dtx_latch_heartbeat", State::heartbeat)
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        self.shared.ioctl("dtx_latch_cancel", State::cancel)
+    }
+
+    fn get_base_info(&self) -> std::io::Result<uapi::BaseInfo> {
+        self.shared.ioctl("dtx_get_base_info", |s| s.base.into())
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        self.shared.ioctl("dtx_get_device_mode", |s| s.mode.into())
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        self.shared.ioctl("dtx_get_latch_status", |s| s.latch.into())
+    }
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut state = self.shared.state();
+
+        loop {
+            if state.shutdown {
+                return Err(nix::errno::Errno::ENODEV.into());
+            }
+
+            let n = Self::with_client(&mut state, self.id, |c| {
+                let n = read_queue(&mut c.queue, buf);
+                c.update_readiness(false);
+                n
+            });
+
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+
+            if self.readiness.is_nonblocking()? {
+                return Err(std::io::ErrorKind::WouldBlock.into());
+            }
+
+            state = self.shared.readable.wait(state).unwrap();
+        }
+    }
+}
+
+impl AsFd for SimDevice {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.readiness.rx.as_fd()
+    }
+}
+
+impl AsRawFd for SimDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.readiness.rx.as_raw_fd()
+    }
+}
+
+impl AsyncRead for SimDevice {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+        let mut state = self.shared.state();
+
+        if state.shutdown {
+            return Poll::Ready(Err(nix::errno::Errno::ENODEV.into()));
+        }
+
+        Self::with_client(&mut state, self.id, |c| {
+            let n = read_queue(&mut c.queue, buf);
+            c.update_readiness(false);
+
+            if n > 0 || buf.is_empty() {
+                Poll::Ready(Ok(n))
+            } else {
+                c.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+
+fn read_queue(queue: &mut VecDeque<u8>, buf: &mut [u8]) -> usize {
+    let n = usize::min(queue.len(), buf.len());
+
+    for (dst, src) in buf.iter_mut().zip(queue.drain(..n)) {
+        *dst = src;
+    }
+
+    n
+}
@@ -0,0 +1,131 @@
+use std::convert::TryFrom;
+
+use tracing::warn;
+
+use crate::{BaseInfo, BaseState, CancelReason, Device, DeviceMode, DtxBackend, Error, Event, LatchStatus};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "change", rename_all = "kebab-case"))]
+pub enum StateChange {
+    Base { old: BaseInfo, new: BaseInfo },
+    DeviceMode { old: DeviceMode, new: DeviceMode },
+    LatchStatus { old: LatchStatus, new: LatchStatus },
+    DetachPending { old: bool, new: bool },
+
+    /// Detachment has been canceled. Reported for every cancel event, even if
+    /// the reason is the same as the last one.
+    Cancel { reason: CancelReason },
+}
+
+
+/// Snapshot of the device state, kept current by applying events.
+///
+/// Whether a detachment is pending cannot be queried from the device and is
+/// assumed to be false initially. Events with unknown values are logged and
+/// otherwise ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceState {
+    pub base: BaseInfo,
+    pub device_mode: DeviceMode,
+    pub latch_status: LatchStatus,
+    pub detach_pending: bool,
+    pub last_cancel: Option<CancelReason>,
+}
+
+impl DeviceState {
+    pub fn new(base: BaseInfo, device_mode: DeviceMode, latch_status: LatchStatus) -> Self {
+        DeviceState {
+            base,
+            device_mode,
+            latch_status,
+            detach_pending: false,
+            last_cancel: None,
+        }
+    }
+
+    pub fn query<F: DtxBackend>(device: &Device<F>) -> Result<Self, Error> {
+        let base = device.get_base_info()?;
+        let mode = device.get_device_mode()?;
+        let latch = device.get_latch_status()?;
+
+        Ok(DeviceState::new(base, mode, latch))
+    }
+
+    pub fn apply(&mut self, event: &Event) -> Vec<StateChange> {
+        let mut changes = Vec::new();
+
+        match event {
+            Event::Request => {
+                changes.extend(self.set_detach_pending(!self.detach_pending));
+            },
+            Event::Cancel { reason } => {
+                changes.extend(self.set_detach_pending(false));
+
+                match CancelReason::try_from(*reason) {
+                    Ok(reason) => {
+                        self.last_cancel = Some(reason);
+                        changes.push(StateChange::Cancel { reason });
+                    },
+                    Err(err) => warn!(target: "sdtx::state", error=%err, "ignoring cancel reason"),
+                }
+            },
+            Event::BaseConnection { state, device_type, id } => match BaseState::try_from(*state) {
+                Ok(state) => {
+                    let base = BaseInfo { state, device_type: *device_type, id: *id };
+
+                    if base != self.base {
+                        changes.push(StateChange::Base { old: self.base, new: base });
+                        self.base = base;
+                    }
+
+                    if state == BaseState::Detached {
+                        changes.extend(self.set_detach_pending(false));
+                    }
+                },
+                Err(err) => warn!(target: "sdtx::state", error=%err, "ignoring base connection event"),
+            },
+            Event::LatchStatus { status } => match LatchStatus::try_from(*status) {
+                Ok(status) => {
+                    let old = self.latch_status;
+
+                    if status != old {
+                        changes.push(StateChange::LatchStatus { old, new: status });
+                        self.latch_status = status;
+                    }
+
+                    // latch closing again after having been opened ends the detachment
+                    if old == LatchStatus::Opened && status == LatchStatus::Closed {
+                        changes.extend(self.set_detach_pending(false));
+                    }
+                },
+                Err(err) => warn!(target: "sdtx::state", error=%err, "ignoring latch status event"),
+            },
+            Event::DeviceMode { mode } => match DeviceMode::try_from(*mode) {
+                Ok(mode) => {
+                    if mode != self.device_mode {
+                        changes.push(StateChange::DeviceMode { old: self.device_mode, new: mode });
+                        self.device_mode = mode;
+                    }
+                },
+                Err(err) => warn!(target: "sdtx::state", error=%err, "ignoring device mode event"),
+            },
+            Event::Unknown { code, .. } => {
+                warn!(target: "sdtx::state", code, "ignoring unknown event");
+            },
+        }
+
+        changes
+    }
+
+    fn set_detach_pending(&mut self, pending: bool) -> Option<StateChange> {
+        if self.detach_pending == pending {
+            return None;
+        }
+
+        let old = std::mem::replace(&mut self.detach_pending, pending);
+        Some(StateChange::DetachPending { old, new: pending })
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::AsyncRead;
+
+use crate::{uapi, DtxBackend};
+
+
+#[derive(Debug)]
+enum Chunk {
+    Data(Vec<u8>),
+    Pending,
+}
+
+/// Backend returning the given data split into reads of the given sizes.
+///
+/// Meant for testing how events are read and framed. The sizes are repeated
+/// as needed, a size of zero makes asynchronous reads return `Pending` once
+/// and is skipped by blocking reads. Once all data has been read, reads
+/// report end of file. All ioctls succeed without doing anything.
+#[derive(Debug)]
+pub struct ChunkedReader {
+    chunks: VecDeque<Chunk>,
+    nonblocking: bool,
+}
+
+impl ChunkedReader {
+    /// # Panics
+    ///
+    /// Panics if there is data but none of the sizes is non-zero.
+    pub fn new(data: &[u8], sizes: &[usize]) -> Self {
+        assert!(data.is_empty() || sizes.iter().any(|n| *n > 0), "chunk sizes must not all be zero");
+
+        let mut chunks = VecDeque::new();
+        let mut sizes = sizes.iter().copied().cycle();
+        let mut data = data;
+
+        while !data.is_empty() {
+            match sizes.next().unwrap() {
+                0 => chunks.push_back(Chunk::Pending),
+                n => {
+                    let (chunk, rest) = data.split_at(usize::min(n, data.len()));
+                    chunks.push_back(Chunk::Data(chunk.to_vec()));
+                    data = rest;
+                },
+            }
+        }
+
+        ChunkedReader { chunks, nonblocking: false }
+    }
+
+    /// Behave like a device file in non-blocking mode.
+    ///
+    /// Blocking reads then fail with `WouldBlock` instead of skipping a
+    /// size of zero, and instead of reporting end of file.
+    pub fn with_nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let chunk = match self.chunks.front_mut() {
+            Some(Chunk::Data(chunk)) => chunk,
+            Some(Chunk::Pending) => {
+                self.chunks.pop_front();
+                return None;
+            },
+            None => return Some(0),
+        };
+
+        let n = usize::min(chunk.len(), buf.len());
+        buf[..n].copy_from_slice(&chunk[..n]);
+        chunk.drain(..n);
+
+        if chunk.is_empty() {
+            self.chunks.pop_front();
+        }
+
+        Some(n)
+    }
+}
+
+impl DtxBackend for ChunkedReader {
+    fn events_enable(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn events_disable(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_lock(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_unlock(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_request(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_confirm(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_heartbeat(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn latch_cancel(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn get_base_info(&self) -> std::io::Result<uapi::BaseInfo> {
+        Ok(uapi::BaseInfo { state: 0, base_id: 0 })
+    }
+
+    fn get_device_mode(&self) -> std::io::Result<u16> {
+        Ok(0)
+    }
+
+    fn get_latch_status(&self) -> std::io::Result<u16> {
+        Ok(0)
+    }
+
+    fn read_events(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match self.read(buf) {
+                Some(0) if self.nonblocking && !buf.is_empty() => {
+                    return Err(std::io::ErrorKind::WouldBlock.into());
+                },
+                Some(n) => return Ok(n),
+                None if self.nonblocking => return Err(std::io::ErrorKind::WouldBlock.into()),
+                None => {},
+            }
+        }
+    }
+}
+
+impl AsyncRead for ChunkedReader {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<std::io::Result<usize>> {
+        match self.get_mut().read(buf) {
+            Some(n) => Poll::Ready(Ok(n)),
+            None => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            },
+        }
+    }
+}
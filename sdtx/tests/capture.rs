@@ -0,0 +1,66 @@
+use sdtx::capture::{Recorder, Replay};
+use sdtx::event::BaseState;
+use sdtx::sim::Simulator;
+use sdtx::{Device, DeviceType, Event};
+
+
+#[test]
+fn replay_recorded_detachment() {
+    let sim = Simulator::new();
+
+    let mut device = Device::from(Recorder::new(sim.open(), Vec::new()).unwrap());
+
+    let base = device.get_base_info().unwrap();
+    let mode = device.get_device_mode().unwrap();
+
+    let mut recorded = Vec::new();
+    {
+        let mut events = device.events().unwrap();
+
+        sim.press_detach_button();
+        recorded.push(events.next().unwrap().unwrap());
+
+        sim.device().latch_confirm().unwrap();
+        sim.remove_base();
+        sim.attach_base(DeviceType::Ssh, 7);
+
+        loop {
+            let event = events.next().unwrap().unwrap();
+            recorded.push(event.clone());
+
+            if let Event::BaseConnection { state: BaseState::Attached, .. } = event {
+                break;
+            }
+        }
+    }
+
+    let (_, capture) = device.into_file().into_inner().unwrap();
+    let capture = capture.unwrap();
+
+    let mut device = Device::from(Replay::from_reader(capture.as_slice()).unwrap());
+
+    assert_eq!(device.get_base_info().unwrap(), base);
+    assert_eq!(device.get_device_mode().unwrap(), mode);
+    assert!(device.get_latch_status().is_err());
+
+    let replayed = device.events().unwrap()
+        .take(recorded.len())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(recorded[0], Event::Request);
+    assert_eq!(replayed, recorded);
+    assert!(device.file().is_finished());
+}
+
+#[test]
+fn truncated_read_record() {
+    let mut capture = b"SDTXCAP1".to_vec();
+    capture.push(0);
+    capture.extend_from_slice(&0u64.to_le_bytes());
+    capture.extend_from_slice(&u32::MAX.to_le_bytes());
+    capture.extend_from_slice(&[0x01, 0x02]);
+
+    let err = Replay::from_reader(capture.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
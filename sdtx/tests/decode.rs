@@ -0,0 +1,131 @@
+use futures::StreamExt;
+
+use sdtx::capture::{Record, Replay};
+use sdtx::event::DeviceMode;
+use sdtx::{DecodeMode, Device, Error, Event, EventDecoder, EventFilterExt, ProtocolError};
+
+
+fn replay() -> Device<Replay> {
+    let events = [
+        Event::Unknown { code: 0x42, data: vec![1, 2] },
+        Event::DeviceMode { mode: DeviceMode::Unknown(9) },
+        Event::Unknown { code: sdtx::uapi::SDTX_EVENT_REQUEST, data: vec![1] },
+        Event::DeviceMode { mode: DeviceMode::Studio },
+    ];
+
+    let records = events.iter().map(|event| Record::Read {
+        time: Default::default(),
+        data: event.to_bytes(),
+    });
+
+    Device::from(Replay::new(records))
+}
+
+fn protocol_error(err: std::io::Error) -> ProtocolError {
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    *err.get_ref().unwrap().downcast_ref::<ProtocolError>().unwrap()
+}
+
+#[test]
+fn lenient() {
+    let mut device = replay();
+    let mut events = device.events().unwrap();
+
+    assert_eq!(events.decode_mode(), DecodeMode::Lenient);
+    assert_eq!(events.next().unwrap().unwrap(), Event::Unknown { code: 0x42, data: vec![1, 2] });
+    assert_eq!(events.next().unwrap().unwrap(), Event::DeviceMode { mode: DeviceMode::Unknown(9) });
+}
+
+#[test]
+fn strict() {
+    let mut device = replay();
+    let mut events = device.events().unwrap().with_decode_mode(DecodeMode::Strict);
+
+    assert_eq!(protocol_error(events.next().unwrap().unwrap_err()), ProtocolError::UnknownEvent(0x42));
+    assert_eq!(protocol_error(events.next().unwrap().unwrap_err()), ProtocolError::InvalidDeviceMode(9));
+    assert_eq!(protocol_error(events.next().unwrap().unwrap_err()), ProtocolError::InvalidEventPayload(1));
+    assert_eq!(events.next().unwrap().unwrap(), Event::DeviceMode { mode: DeviceMode::Studio });
+
+    let mut device = replay();
+    let mut modes = device.events().unwrap().with_decode_mode(DecodeMode::Strict).device_mode_changes();
+
+    assert!(matches!(
+        modes.next().unwrap(),
+        Err(Error::ProtocolError { source: ProtocolError::UnknownEvent(0x42) })
+    ));
+}
+
+#[test]
+fn lossy() {
+    let mut device = replay();
+    let mut events = device.events().unwrap().with_decode_mode(DecodeMode::Lossy);
+
+    assert_eq!(events.next().unwrap().unwrap(), Event::DeviceMode { mode: DeviceMode::Studio });
+
+    let mut device = replay();
+    let mut events = device.events_async().unwrap().with_decode_mode(DecodeMode::Lossy);
+
+    let event = futures::executor::block_on(events.next()).unwrap().unwrap();
+    assert_eq!(event, Event::DeviceMode { mode: DeviceMode::Studio });
+}
+
+#[test]
+fn decoder_split_and_batched() {
+    let events = [
+        Event::Request,
+        Event::DeviceMode { mode: DeviceMode::Laptop },
+        Event::Unknown { code: 0x42, data: vec![1; 100] },
+    ];
+    let data: Vec<u8> = events.iter().flat_map(Event::to_bytes).collect();
+
+    for size in [1, 3, 7, data.len()] {
+        let mut decoder = EventDecoder::new();
+        let mut decoded = Vec::new();
+
+        for chunk in data.chunks(size) {
+            decoder.feed(chunk);
+            decoded.extend(decoder.by_ref().map(Result::unwrap));
+        }
+
+        assert_eq!(decoded, events);
+        assert!(decoder.is_empty());
+        decoder.finish().unwrap();
+    }
+}
+
+#[test]
+fn decoder_max_payload_len() {
+    let mut data = Event::Unknown { code: 0x42, data: vec![1; 33] }.to_bytes();
+    data.extend(Event::Request.to_bytes());
+
+    let mut decoder = EventDecoder::new().with_max_payload_len(32);
+    decoder.feed(&data[..10]);
+
+    assert_eq!(protocol_error(decoder.next_event().unwrap().unwrap_err()), ProtocolError::PayloadTooLarge(33));
+    assert!(decoder.next_event().is_none());
+    assert!(!decoder.is_empty());
+
+    decoder.feed(&data[10..]);
+    assert_eq!(decoder.next_event().unwrap().unwrap(), Event::Request);
+    assert!(decoder.next_event().is_none());
+
+    let mut decoder = EventDecoder::new().with_max_payload_len(32).with_decode_mode(DecodeMode::Lossy);
+    decoder.feed(&data);
+
+    assert_eq!(decoder.next_event().unwrap().unwrap(), Event::Request);
+}
+
+#[test]
+fn decoder_finish() {
+    let data = Event::Request.to_bytes();
+
+    let mut decoder = EventDecoder::new();
+    decoder.feed(&data[..2]);
+
+    assert!(decoder.next_event().is_none());
+    assert_eq!(decoder.finish().unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    assert!(decoder.is_empty());
+
+    decoder.feed(&data);
+    assert_eq!(decoder.next_event().unwrap().unwrap(), Event::Request);
+}
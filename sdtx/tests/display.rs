@@ -0,0 +1,45 @@
+use sdtx::event::{BaseState, CancelReason, DeviceMode, LatchStatus};
+use sdtx::{DeviceType, Event, HardwareError, RuntimeError};
+
+
+#[test]
+fn events() {
+    let events = [
+        (Event::Request, "Request"),
+        (
+            Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Timeout) },
+            "Cancel: Detach operation timed out",
+        ),
+        (
+            Event::Cancel { reason: CancelReason::Unknown(0x3042) },
+            "Cancel: Unknown (0x3042)",
+        ),
+        (
+            Event::BaseConnection { state: BaseState::Attached, device_type: DeviceType::Ssh, id: 7 },
+            "BaseConnection: Attached (type: SSH, id: 0x07)",
+        ),
+        (
+            Event::LatchStatus { status: LatchStatus::Error(HardwareError::FailedToOpen) },
+            "LatchStatus: Error: Failed to open latch",
+        ),
+        (
+            Event::DeviceMode { mode: DeviceMode::Unknown(5) },
+            "DeviceMode: Unknown (0x0005)",
+        ),
+        (
+            Event::Unknown { code: 0x42, data: vec![1, 0xff] },
+            "Unknown: code 0x0042, data [01, ff]",
+        ),
+    ];
+
+    for (event, text) in events {
+        assert_eq!(event.to_string(), text);
+    }
+}
+
+#[test]
+fn state_types() {
+    assert_eq!(sdtx::CancelReason::Hardware(HardwareError::FailedToClose).to_string(), "Failed to close latch");
+    assert_eq!(BaseState::Unknown(0x42).to_string(), "Unknown (0x0042)");
+    assert_eq!(LatchStatus::Opened.to_string(), "Opened");
+}
@@ -0,0 +1,142 @@
+use sdtx::event::{BaseState, CancelReason, DeviceMode, LatchStatus};
+use sdtx::{DeviceType, Event, HardwareError, RuntimeError};
+
+
+fn decode(bytes: &[u8]) -> Event {
+    let length = u16::from_ne_bytes([bytes[0], bytes[1]]) as usize;
+    let code = u16::from_ne_bytes([bytes[2], bytes[3]]);
+
+    assert_eq!(bytes.len(), 4 + length);
+    Event::from_data(code, &bytes[4..])
+}
+
+#[test]
+fn round_trip() {
+    let events = [
+        Event::Request,
+        Event::Cancel { reason: CancelReason::Runtime(RuntimeError::NotFeasible) },
+        Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Timeout) },
+        Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Unknown(0x42)) },
+        Event::Cancel { reason: CancelReason::Hardware(HardwareError::FailedToOpen) },
+        Event::Cancel { reason: CancelReason::Hardware(HardwareError::FailedToRemainOpen) },
+        Event::Cancel { reason: CancelReason::Hardware(HardwareError::FailedToClose) },
+        Event::Cancel { reason: CancelReason::Hardware(HardwareError::Unknown(0x42)) },
+        Event::Cancel { reason: CancelReason::Unknown(0x3042) },
+        Event::BaseConnection { state: BaseState::Detached, device_type: DeviceType::Hid, id: 0 },
+        Event::BaseConnection { state: BaseState::Attached, device_type: DeviceType::Ssh, id: 0x12 },
+        Event::BaseConnection { state: BaseState::NotFeasible, device_type: DeviceType::Unknown(0x7), id: 0xff },
+        Event::BaseConnection { state: BaseState::Unknown(0x4242), device_type: DeviceType::Hid, id: 1 },
+        Event::LatchStatus { status: LatchStatus::Closed },
+        Event::LatchStatus { status: LatchStatus::Opened },
+        Event::LatchStatus { status: LatchStatus::Error(HardwareError::FailedToOpen) },
+        Event::LatchStatus { status: LatchStatus::Error(HardwareError::Unknown(0x42)) },
+        Event::LatchStatus { status: LatchStatus::Unknown(0x0042) },
+        Event::LatchStatus { status: LatchStatus::Unknown(0x1001) },
+        Event::DeviceMode { mode: DeviceMode::Tablet },
+        Event::DeviceMode { mode: DeviceMode::Laptop },
+        Event::DeviceMode { mode: DeviceMode::Studio },
+        Event::DeviceMode { mode: DeviceMode::Unknown(0x42) },
+        Event::Unknown { code: 0, data: vec![] },
+        Event::Unknown { code: 0x4242, data: vec![1, 2, 3, 4, 5] },
+        Event::Unknown { code: sdtx::uapi::SDTX_EVENT_REQUEST, data: vec![1] },
+        Event::Unknown { code: sdtx::uapi::SDTX_EVENT_CANCEL, data: vec![1, 2, 3] },
+        Event::Unknown { code: sdtx::uapi::SDTX_EVENT_DEVICE_MODE, data: vec![0; 64] },
+    ];
+
+    for event in events {
+        assert_eq!(decode(&event.to_bytes()), event);
+        assert_eq!(event.normalized(), event);
+    }
+}
+
+#[test]
+fn encode_into_appends() {
+    let mut buf = vec![0xaa];
+
+    Event::Request.encode_into(&mut buf);
+    Event::DeviceMode { mode: DeviceMode::Laptop }.encode_into(&mut buf);
+
+    let mut expected = vec![0xaa];
+    expected.extend_from_slice(&0u16.to_ne_bytes());
+    expected.extend_from_slice(&sdtx::uapi::SDTX_EVENT_REQUEST.to_ne_bytes());
+    expected.extend_from_slice(&2u16.to_ne_bytes());
+    expected.extend_from_slice(&sdtx::uapi::SDTX_EVENT_DEVICE_MODE.to_ne_bytes());
+    expected.extend_from_slice(&sdtx::uapi::SDTX_DEVICE_MODE_LAPTOP.to_ne_bytes());
+
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn reserved_bits_round_trip() {
+    // found by fuzzing: reserved bits used to be dropped when decoding
+    let payloads = [
+        (sdtx::uapi::SDTX_EVENT_CANCEL, [0x01, 0x2d]),
+        (sdtx::uapi::SDTX_EVENT_LATCH_STATUS, [0x01, 0x2d]),
+        (sdtx::uapi::SDTX_EVENT_CANCEL, [0x01, 0x1d]),
+    ];
+
+    for (code, data) in payloads {
+        let event = Event::from_data(code, &data);
+        assert_eq!(&event.to_bytes()[4..], &data);
+    }
+
+    let data = [0x03, 0x00, 0x02, 0x41];
+    let event = Event::from_data(sdtx::uapi::SDTX_EVENT_BASE_CONNECTION, &data);
+
+    assert_eq!(event, Event::Unknown { code: sdtx::uapi::SDTX_EVENT_BASE_CONNECTION, data: data.to_vec() });
+}
+
+#[test]
+fn normalize_aliased_unknown_values() {
+    let events = [
+        (
+            Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Unknown(0x01)) },
+            Event::Cancel { reason: CancelReason::Runtime(RuntimeError::NotFeasible) },
+        ),
+        (
+            Event::Cancel { reason: CancelReason::Hardware(HardwareError::Unknown(0x03)) },
+            Event::Cancel { reason: CancelReason::Hardware(HardwareError::FailedToClose) },
+        ),
+        (
+            Event::Cancel { reason: CancelReason::Unknown(0x1002) },
+            Event::Cancel { reason: CancelReason::Runtime(RuntimeError::Timeout) },
+        ),
+        (
+            Event::BaseConnection { state: BaseState::Unknown(0x01), device_type: DeviceType::Unknown(0x01), id: 3 },
+            Event::BaseConnection { state: BaseState::Attached, device_type: DeviceType::Hid, id: 3 },
+        ),
+        (
+            Event::LatchStatus { status: LatchStatus::Unknown(0x0001) },
+            Event::LatchStatus { status: LatchStatus::Opened },
+        ),
+        (
+            Event::DeviceMode { mode: DeviceMode::Unknown(0) },
+            Event::DeviceMode { mode: DeviceMode::Tablet },
+        ),
+        (
+            Event::Unknown { code: sdtx::uapi::SDTX_EVENT_REQUEST, data: vec![] },
+            Event::Request,
+        ),
+    ];
+
+    for (event, normalized) in events {
+        assert_eq!(event.normalized(), normalized);
+        assert_eq!(decode(&event.to_bytes()), normalized);
+        assert_eq!(normalized.to_bytes(), event.to_bytes());
+    }
+}
+
+#[test]
+fn truncate_out_of_range_values() {
+    let event = Event::BaseConnection { state: BaseState::Attached, device_type: DeviceType::Unknown(0x42), id: 7 };
+    let normalized = Event::BaseConnection { state: BaseState::Attached, device_type: DeviceType::Ssh, id: 7 };
+
+    assert_eq!(event.normalized(), normalized);
+    assert_eq!(decode(&event.to_bytes()), normalized);
+
+    let event = Event::Unknown { code: 0x4242, data: vec![7; u16::MAX as usize + 3] };
+    let normalized = Event::Unknown { code: 0x4242, data: vec![7; u16::MAX as usize] };
+
+    assert_eq!(event.normalized(), normalized);
+    assert_eq!(decode(&event.to_bytes()), normalized);
+}
@@ -0,0 +1,55 @@
+use sdtx::capture::{Record, Replay};
+use sdtx::event::{self, BaseState};
+use sdtx::sim::Simulator;
+use sdtx::{Device, DeviceMode, Error, Event, EventFilterExt, ProtocolError};
+
+
+fn replay(events: &[Event]) -> Device<Replay> {
+    let records = events.iter().map(|event| Record::Read {
+        time: Default::default(),
+        data: event.to_bytes(),
+    });
+
+    Device::from(Replay::new(records))
+}
+
+#[test]
+fn device_mode_changes() {
+    let sim = Simulator::new();
+    let mut device = sim.device();
+    let mut modes = device.events().unwrap().device_mode_changes();
+
+    sim.press_detach_button();
+    sim.set_device_mode(DeviceMode::Studio);
+
+    assert_eq!(modes.next().unwrap().unwrap(), DeviceMode::Studio);
+}
+
+#[test]
+fn unknown_values() {
+    let mut device = replay(&[
+        Event::Request,
+        Event::DeviceMode { mode: event::DeviceMode::Unknown(9) },
+    ]);
+
+    let mut modes = device.events().unwrap().device_mode_changes();
+    assert!(matches!(
+        modes.next().unwrap(),
+        Err(Error::ProtocolError { source: ProtocolError::InvalidDeviceMode(9) })
+    ));
+    drop(modes);
+
+    let mut device = replay(&[
+        Event::BaseConnection { state: BaseState::Unknown(7), device_type: sdtx::DeviceType::Ssh, id: 1 },
+        Event::Request,
+        Event::BaseConnection { state: BaseState::Attached, device_type: sdtx::DeviceType::Ssh, id: 1 },
+    ]);
+
+    let mut bases = device.events().unwrap().base_connections();
+    assert!(matches!(
+        bases.next().unwrap(),
+        Err(Error::ProtocolError { source: ProtocolError::InvalidBaseState(7) })
+    ));
+    assert_eq!(bases.next().unwrap().unwrap().state, sdtx::BaseState::Attached);
+    assert!(matches!(bases.next().unwrap(), Err(Error::IoError { .. })));
+}
@@ -0,0 +1,184 @@
+use futures::{FutureExt, StreamExt};
+
+use sdtx::capture::{Record, Replay};
+use sdtx::event::{BaseState, DeviceMode, LatchStatus};
+use sdtx::testing::ChunkedReader;
+use sdtx::{Device, DeviceType, Event};
+
+
+fn events() -> Vec<Event> {
+    vec![
+        Event::Request,
+        Event::BaseConnection { state: BaseState::Attached, device_type: DeviceType::Ssh, id: 3 },
+        Event::LatchStatus { status: LatchStatus::Opened },
+        Event::Unknown { code: 0x42, data: vec![7; 200] },
+        Event::DeviceMode { mode: DeviceMode::Laptop },
+    ]
+}
+
+/// Replay the encoded events, split into reads of the given sizes.
+fn replay(events: &[Event], sizes: &[usize]) -> Device<Replay> {
+    let data: Vec<u8> = events.iter().flat_map(Event::to_bytes).collect();
+
+    let mut reads = Vec::new();
+    let mut rest = data.as_slice();
+    for size in sizes.iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+
+        let (read, r) = rest.split_at(usize::min(*size, rest.len()));
+        reads.push(Record::Read { time: Default::default(), data: read.to_vec() });
+        rest = r;
+    }
+
+    Device::from(Replay::new(reads))
+}
+
+fn check_async(events: &[Event], sizes: &[usize]) {
+    // replays never block, so every event must be available immediately
+    let mut device = replay(events, sizes);
+    let mut stream = device.events_async().unwrap();
+
+    for event in events {
+        assert_eq!(&stream.next().now_or_never().unwrap().unwrap().unwrap(), event);
+    }
+    drop(stream);
+
+    let mut device = replay(events, sizes);
+    let mut stream = device.events_async().unwrap();
+
+    for event in events {
+        assert_eq!(&stream.read_next().now_or_never().unwrap().unwrap(), event);
+    }
+}
+
+fn check_blocking(events: &[Event], sizes: &[usize]) {
+    let mut device = replay(events, sizes);
+    let mut stream = device.events().unwrap();
+
+    for event in events {
+        assert_eq!(&stream.read_next_blocking().unwrap(), event);
+    }
+}
+
+#[test]
+fn multiple_events_per_read() {
+    let events = events();
+
+    check_async(&events, &[usize::MAX]);
+    check_blocking(&events, &[usize::MAX]);
+}
+
+#[test]
+fn events_split_across_reads() {
+    let events = events();
+
+    for sizes in [&[1][..], &[3], &[5, 2], &[130, 7]] {
+        check_async(&events, sizes);
+        check_blocking(&events, sizes);
+    }
+}
+
+#[test]
+fn truncated_event() {
+    let mut data = Event::Request.to_bytes();
+    data.extend_from_slice(&Event::DeviceMode { mode: DeviceMode::Tablet }.to_bytes()[..5]);
+
+    let replay = || Device::from(Replay::new(vec![Record::Read { time: Default::default(), data: data.clone() }]));
+
+    let mut device = replay();
+    let mut stream = device.events_async().unwrap();
+
+    assert_eq!(stream.next().now_or_never().unwrap().unwrap().unwrap(), Event::Request);
+    let err = stream.next().now_or_never().unwrap().unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert!(stream.next().now_or_never().unwrap().is_none());
+    drop(stream);
+
+    let mut device = replay();
+    let mut stream = device.events_async().unwrap();
+
+    assert_eq!(stream.read_next().now_or_never().unwrap().unwrap(), Event::Request);
+    let err = stream.read_next().now_or_never().unwrap().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    drop(stream);
+
+    let mut device = replay();
+    let mut stream = device.events().unwrap();
+
+    assert_eq!(stream.read_next_blocking().unwrap(), Event::Request);
+    let err = stream.read_next_blocking().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn end_of_stream() {
+    let mut device = replay(&events(), &[usize::MAX]);
+    let stream = device.events_async().unwrap();
+
+    let received = futures::executor::block_on(stream.collect::<Vec<_>>());
+    let received = received.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+
+    assert_eq!(received, events());
+}
+
+
+/// One byte per read, with `Pending` in between.
+fn trickle(events: &[Event]) -> Device<ChunkedReader> {
+    let data: Vec<u8> = events.iter().flat_map(Event::to_bytes).collect();
+    Device::from(ChunkedReader::new(&data, &[0, 1]))
+}
+
+#[test]
+fn one_byte_at_a_time() {
+    let mut device = trickle(&events());
+    let stream = device.events_async().unwrap();
+
+    let received = futures::executor::block_on(stream.collect::<Vec<_>>());
+    let received = received.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+
+    assert_eq!(received, events());
+
+    let mut device = trickle(&events());
+    let mut stream = device.events().unwrap();
+
+    for event in events() {
+        assert_eq!(stream.read_next_blocking().unwrap(), event);
+    }
+}
+
+#[test]
+fn read_next_cancel_safe() {
+    let mut device = trickle(&events());
+    let mut stream = device.events_async().unwrap();
+
+    // poll a new future each time and drop it if it is not ready yet
+    for event in events() {
+        let received = loop {
+            if let Some(result) = stream.read_next().now_or_never() {
+                break result.unwrap();
+            }
+        };
+
+        assert_eq!(received, event);
+    }
+
+    let err = futures::executor::block_on(stream.read_next()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn try_read_events() {
+    let mut data: Vec<u8> = events().iter().flat_map(Event::to_bytes).collect();
+    data.truncate(data.len() - 1);
+
+    let mut device = Device::from(ChunkedReader::new(&data, &[1]).with_nonblocking(true));
+    let mut stream = device.events().unwrap();
+
+    let mut received = vec![Event::Request];
+    assert_eq!(stream.try_read_events(&mut received).unwrap(), events().len() - 1);
+    assert_eq!(received[1..], events()[..events().len() - 1]);
+
+    assert_eq!(stream.try_read_events(&mut received).unwrap(), 0);
+}
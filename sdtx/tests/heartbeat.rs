@@ -0,0 +1,126 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::task::ArcWake;
+
+use sdtx::sim::Simulator;
+use sdtx::{Event, Heartbeat, HeartbeatThread};
+
+
+/// Wait until the simulator has received a heartbeat after this call.
+fn wait_for_heartbeat(sim: &Simulator) {
+    let count = sim.heartbeats();
+    let deadline = Instant::now() + Duration::from_secs(10);
+
+    while sim.heartbeats() == count {
+        assert!(Instant::now() < deadline, "no heartbeat received");
+        std::thread::yield_now();
+    }
+}
+
+struct Noop;
+
+impl ArcWake for Noop {
+    fn wake_by_ref(_arc_self: &Arc<Self>) {}
+}
+
+#[test]
+fn run_keeps_detachment_alive() {
+    let sim = Simulator::new();
+    let device = sim.device();
+
+    // with the latch locked, a missed heartbeat cancels the detachment
+    device.latch_lock().unwrap();
+    sim.set_timeout(Duration::from_secs(2));
+    sim.press_detach_button();
+
+    let heartbeat = Heartbeat::new();
+    heartbeat.handle(&Event::Request);
+
+    let mut slept = Duration::ZERO;
+    let sleep = |interval| {
+        sim.advance(interval);
+        slept += interval;
+
+        if slept >= Duration::from_secs(10) {
+            heartbeat.close();
+        }
+
+        futures::future::ready(())
+    };
+
+    futures::executor::block_on(heartbeat.run(&device, Duration::from_secs(1), sleep));
+
+    assert!(sim.is_detach_in_progress());
+
+    sim.advance(Duration::from_secs(2));
+    assert!(!sim.is_detach_in_progress());
+}
+
+#[test]
+fn stop_on_cancel() {
+    let heartbeat = Heartbeat::new();
+
+    heartbeat.handle(&Event::Request);
+    assert!(heartbeat.is_active());
+
+    // a second request aborts the detachment
+    heartbeat.handle(&Event::Request);
+    assert!(!heartbeat.is_active());
+
+    heartbeat.handle(&Event::Request);
+    heartbeat.handle(&Event::Cancel { reason: sdtx::event::CancelReason::Unknown(0) });
+    assert!(!heartbeat.is_active());
+}
+
+#[test]
+fn dropped_runs_release_wakers() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let heartbeat = Heartbeat::new();
+
+    let tokens: Vec<_> = (0..8).map(|_| Arc::new(Noop)).collect();
+
+    for token in &tokens {
+        let waker = futures::task::waker(token.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let run = heartbeat.run(&device, Duration::from_secs(1), |_| futures::future::ready(()));
+        futures::pin_mut!(run);
+
+        assert_eq!(run.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(run.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(Arc::strong_count(token), 3);
+    }
+
+    for token in &tokens {
+        assert_eq!(Arc::strong_count(token), 1);
+    }
+}
+
+#[test]
+fn thread_keeps_detachment_alive() {
+    let sim = Simulator::new();
+    let device = Arc::new(sim.device());
+
+    device.latch_lock().unwrap();
+    sim.set_timeout(Duration::from_secs(2));
+    sim.press_detach_button();
+
+    let thread = HeartbeatThread::spawn(device, Duration::from_millis(1)).unwrap();
+    thread.handle(&Event::Request);
+
+    for _ in 0..10 {
+        sim.advance(Duration::from_secs(1));
+        wait_for_heartbeat(&sim);
+    }
+
+    assert!(sim.is_detach_in_progress());
+
+    drop(thread);
+
+    sim.advance(Duration::from_secs(2));
+    assert!(!sim.is_detach_in_progress());
+}
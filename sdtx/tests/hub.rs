@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+
+use sdtx::event::DeviceMode;
+use sdtx::hub::TryRecvError;
+use sdtx::sim::Simulator;
+use sdtx::{Event, EventHub, LagPolicy};
+use sdtx::DeviceMode as SimMode;
+
+
+fn mode(mode: DeviceMode) -> Event {
+    Event::DeviceMode { mode }
+}
+
+#[test]
+fn drop_oldest() {
+    let hub = EventHub::with_capacity(2, LagPolicy::DropOldest);
+    let slow = hub.subscribe();
+    let fast = hub.subscribe();
+
+    for m in [DeviceMode::Tablet, DeviceMode::Laptop, DeviceMode::Studio] {
+        assert!(hub.publish(&mode(m)));
+        assert_eq!(fast.try_recv(), Ok(mode(m)));
+    }
+
+    hub.close();
+
+    assert_eq!(slow.lagged(), 1);
+    assert_eq!(fast.lagged(), 0);
+    assert_eq!(Iterator::collect::<Vec<_>>(slow), [mode(DeviceMode::Laptop), mode(DeviceMode::Studio)]);
+    assert_eq!(fast.try_recv(), Err(TryRecvError::Closed));
+}
+
+#[test]
+fn spawn_drop_oldest_lag_counts() {
+    let sim = Simulator::new();
+    let thread = EventHub::spawn(sim.device(), 2, LagPolicy::DropOldest).unwrap();
+
+    let first = thread.subscribe();
+    let second = thread.subscribe();
+    let fast = thread.subscribe();
+
+    let modes = [SimMode::Tablet, SimMode::Laptop, SimMode::Studio];
+    let publish = |m: SimMode| {
+        sim.set_device_mode(m);
+
+        // events are queued for all subscribers at once
+        assert_eq!(fast.recv(), Some(Event::DeviceMode { mode: m.into() }));
+    };
+
+    for m in modes.iter().chain(&modes[..1]) {
+        publish(*m);
+    }
+
+    assert_eq!(first.lagged(), 2);
+    assert_eq!(second.lagged(), 2);
+    assert_eq!(first.try_recv(), Ok(mode(DeviceMode::Studio)));
+    assert_eq!(first.try_recv(), Ok(mode(DeviceMode::Tablet)));
+    assert_eq!(first.try_recv(), Err(TryRecvError::Empty));
+
+    for m in &modes[1..] {
+        publish(*m);
+    }
+
+    sim.shutdown();
+
+    assert_eq!(first.lagged(), 2);
+    assert_eq!(second.lagged(), 4);
+    assert_eq!(fast.lagged(), 0);
+    assert_eq!(Iterator::collect::<Vec<_>>(first), [mode(DeviceMode::Laptop), mode(DeviceMode::Studio)]);
+    assert_eq!(Iterator::collect::<Vec<_>>(second), [mode(DeviceMode::Laptop), mode(DeviceMode::Studio)]);
+    assert_eq!(fast.recv(), None);
+    assert!(thread.hub().is_closed());
+    assert_eq!(thread.join().unwrap_err().raw_os_error(), Some(nix::libc::ENODEV));
+}
+
+#[test]
+fn backpressure() {
+    let hub = EventHub::with_capacity(1, LagPolicy::Backpressure);
+    let sub = hub.subscribe();
+
+    let publisher = {
+        let hub = hub.clone();
+
+        std::thread::spawn(move || {
+            hub.forward_blocking([DeviceMode::Tablet, DeviceMode::Laptop, DeviceMode::Studio]
+                .iter()
+                .map(|m| Ok(mode(*m))))
+        })
+    };
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(!publisher.is_finished());
+
+    let events = Iterator::collect::<Vec<_>>(sub);
+    publisher.join().unwrap().unwrap();
+
+    assert_eq!(events.len(), 3);
+}
+
+#[test]
+fn spawn_with_async_subscriber() {
+    let sim = Simulator::new();
+    let thread = EventHub::spawn(sim.device(), 8, LagPolicy::DropOldest).unwrap();
+
+    let sync = thread.subscribe();
+    let mut stream = thread.subscribe();
+
+    sim.press_detach_button();
+
+    assert_eq!(sync.recv(), Some(Event::Request));
+    assert_eq!(futures::executor::block_on(StreamExt::next(&mut stream)), Some(Event::Request));
+
+    thread.hub().close();
+
+    assert_eq!(sync.recv(), None);
+    assert_eq!(futures::executor::block_on(StreamExt::next(&mut stream)), None);
+    thread.join().unwrap();
+}
+
+#[test]
+fn close_idle_hub_stops_thread() {
+    let sim = Simulator::new();
+    let thread = EventHub::spawn(sim.device(), 8, LagPolicy::DropOldest).unwrap();
+    let sub = thread.subscribe();
+
+    thread.hub().close();
+
+    assert_eq!(sub.recv(), None);
+    thread.join().unwrap();
+}
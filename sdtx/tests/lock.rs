@@ -0,0 +1,136 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Level, Metadata, Subscriber};
+
+use sdtx::sim::Simulator;
+use sdtx::SharedLatch;
+
+
+/// Subscriber collecting target and message of all warnings.
+#[derive(Debug, Clone, Default)]
+struct Warnings(Arc<Mutex<Vec<(String, String)>>>);
+
+impl Warnings {
+    fn collect(f: impl FnOnce()) -> Vec<(String, String)> {
+        let warnings = Warnings::default();
+        tracing::subscriber::with_default(warnings.clone(), f);
+
+        let messages = warnings.0.lock().unwrap();
+        messages.clone()
+    }
+}
+
+impl Subscriber for Warnings {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() == Level::WARN
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut message = Message::default();
+        event.record(&mut message);
+
+        let target = event.metadata().target().to_owned();
+        self.0.lock().unwrap().push((target, message.0));
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+
+#[test]
+fn guard() {
+    let sim = Simulator::new();
+    let device = sim.device();
+
+    let guard = device.lock_guard().unwrap();
+    assert!(sim.is_locked());
+    drop(guard);
+    assert!(!sim.is_locked());
+
+    let guard = device.lock_guard().unwrap();
+    assert!(sim.is_locked());
+    guard.unlock().unwrap();
+    assert!(!sim.is_locked());
+}
+
+#[test]
+fn guards_do_not_nest() {
+    let sim = Simulator::new();
+    let device = sim.device();
+
+    let first = device.lock_guard().unwrap();
+    let second = device.lock_guard().unwrap();
+
+    drop(first);
+    assert!(!sim.is_locked());
+
+    drop(second);
+    assert!(!sim.is_locked());
+}
+
+#[test]
+fn shared_latch() {
+    let sim = Simulator::new();
+    let latch = SharedLatch::new(Arc::new(sim.device()));
+
+    let first = latch.acquire().unwrap();
+    let second = latch.clone().acquire().unwrap();
+    assert_eq!(latch.holders(), 2);
+    assert!(sim.is_locked());
+
+    drop(first);
+    assert_eq!(latch.holders(), 1);
+    assert!(sim.is_locked());
+
+    drop(second);
+    assert_eq!(latch.holders(), 0);
+    assert!(!sim.is_locked());
+}
+
+#[test]
+fn unlock_failure_on_drop() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let latch = SharedLatch::new(Arc::new(sim.device()));
+
+    let guard = device.lock_guard().unwrap();
+    let shared = latch.acquire().unwrap();
+
+    sim.shutdown();
+
+    let warnings = Warnings::collect(|| {
+        drop(guard);
+        drop(shared);
+    });
+
+    assert_eq!(warnings.len(), 2);
+    for (target, message) in &warnings {
+        assert_eq!(target, "sdtx::ioctl");
+        assert_eq!(message, "failed to unlock latch on drop");
+    }
+    assert_eq!(latch.holders(), 0);
+}
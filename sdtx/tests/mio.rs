@@ -0,0 +1,52 @@
+#![cfg(feature = "mio")]
+
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use mio::{Events, Interest, Poll, Token};
+
+use sdtx::capture::Recorder;
+use sdtx::Device;
+
+
+#[test]
+fn register_device() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    rx.set_nonblocking(true).unwrap();
+
+    let mut device = Device::from(rx);
+
+    let mut poll = Poll::new().unwrap();
+    poll.registry().register(&mut device, Token(7), Interest::READABLE).unwrap();
+
+    let mut events = Events::with_capacity(4);
+    poll.poll(&mut events, Some(Duration::ZERO)).unwrap();
+    assert!(events.is_empty());
+
+    tx.write_all(&sdtx::Event::Request.to_bytes()).unwrap();
+
+    poll.poll(&mut events, Some(Duration::from_secs(5))).unwrap();
+    let event = events.iter().next().unwrap();
+    assert_eq!(event.token(), Token(7));
+    assert!(event.is_readable());
+
+    poll.registry().deregister(&mut device).unwrap();
+}
+
+#[test]
+fn register_recorder() {
+    let (mut tx, rx) = UnixStream::pair().unwrap();
+    rx.set_nonblocking(true).unwrap();
+
+    let mut device = Device::from(Recorder::new(rx, Vec::new()).unwrap());
+
+    let mut poll = Poll::new().unwrap();
+    poll.registry().register(&mut device, Token(3), Interest::READABLE).unwrap();
+
+    tx.write_all(&sdtx::Event::Request.to_bytes()).unwrap();
+
+    let mut events = Events::with_capacity(4);
+    poll.poll(&mut events, Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(events.iter().next().unwrap().token(), Token(3));
+}
@@ -0,0 +1,53 @@
+use std::fs::File;
+
+use sdtx::capture::Replay;
+use sdtx::event::LatchStatus;
+use sdtx::sim::{SimDevice, Simulator};
+use sdtx::{DtxBackend, Event, OwnedAsyncEventStream, OwnedEventStream};
+
+
+fn assert_send_static<T: Send + 'static>() {}
+
+#[test]
+fn owned_streams_are_send_static() {
+    assert_send_static::<OwnedEventStream<File>>();
+    assert_send_static::<OwnedEventStream<SimDevice>>();
+    assert_send_static::<OwnedEventStream<Replay>>();
+    assert_send_static::<OwnedAsyncEventStream<SimDevice>>();
+    assert_send_static::<OwnedAsyncEventStream<Replay>>();
+}
+
+#[test]
+fn read_in_thread_after_clone() {
+    let sim = Simulator::new();
+    let device = sim.device();
+
+    let events = device.try_clone().unwrap().into_events().unwrap();
+    let reader = std::thread::spawn(move || events.take(2).collect::<Result<Vec<_>, _>>());
+
+    sim.press_detach_button();
+
+    // the device is still usable for commands while the stream is being read
+    assert!(device.get_base_info().is_ok());
+    device.latch_confirm().unwrap();
+
+    let events = reader.join().unwrap().unwrap();
+    assert_eq!(events, [Event::Request, Event::LatchStatus { status: LatchStatus::Opened }]);
+}
+
+#[test]
+fn clone_shares_event_state() {
+    let sim = Simulator::new();
+    let mut device = sim.device();
+    let clone = device.try_clone().unwrap();
+
+    // events enabled via the clone are delivered to the original handle
+    clone.events_enable().unwrap();
+    drop(clone);
+
+    sim.press_detach_button();
+
+    let mut buf = [0; 16];
+    let n = device.file_mut().read_events(&mut buf).unwrap();
+    assert_eq!(&buf[..n], &Event::Request.to_bytes()[..]);
+}
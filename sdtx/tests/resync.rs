@@ -0,0 +1,47 @@
+use sdtx::event::{BaseState, DeviceMode, LatchStatus};
+use sdtx::sim::Simulator;
+use sdtx::{Event, ResyncedEvent};
+
+
+fn synthesized(event: Event) -> ResyncedEvent {
+    ResyncedEvent { event, synthesized: true }
+}
+
+#[test]
+fn resync_after_restart() {
+    let sim = Simulator::new();
+    let base = sim.base_info();
+
+    let mut device = sim.device();
+
+    let mut events = device.events().unwrap().resynced(None).unwrap();
+    let initial = (0..3).map(|_| events.next().unwrap().unwrap()).collect::<Vec<_>>();
+    let state = *events.state();
+    drop(events);
+
+    assert_eq!(initial, [
+        synthesized(Event::BaseConnection { state: BaseState::Attached, device_type: base.device_type, id: base.id }),
+        synthesized(Event::DeviceMode { mode: DeviceMode::Laptop }),
+        synthesized(Event::LatchStatus { status: LatchStatus::Closed }),
+    ]);
+
+    // not observed, events are disabled
+    sim.remove_base();
+
+    let mut events = device.events().unwrap().resynced(Some(state)).unwrap();
+    let resynced = (0..2).map(|_| events.next().unwrap().unwrap()).collect::<Vec<_>>();
+
+    assert_eq!(resynced, [
+        synthesized(Event::BaseConnection { state: BaseState::Detached, device_type: base.device_type, id: base.id }),
+        synthesized(Event::DeviceMode { mode: DeviceMode::Tablet }),
+    ]);
+
+    assert_eq!(events.state().base.state, sdtx::BaseState::Detached);
+    assert_eq!(events.state().device_mode, sdtx::DeviceMode::Tablet);
+
+    sim.attach_base(base.device_type, base.id);
+
+    let received = events.next().unwrap().unwrap();
+    assert!(!received.synthesized);
+    assert_eq!(events.state().base, base);
+}
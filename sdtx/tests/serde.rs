@@ -0,0 +1,102 @@
+#![cfg(feature = "serde")]
+
+use std::fmt::Debug;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use sdtx::{event, BaseInfo, BaseState, CancelReason, DeviceMode, DeviceType, Event};
+use sdtx::{HardwareError, LatchStatus, RuntimeError};
+
+
+fn check<T>(value: T, expected: Value)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    let serialized = serde_json::to_value(&value).unwrap();
+    assert_eq!(serialized, expected);
+
+    let deserialized: T = serde_json::from_value(serialized).unwrap();
+    assert_eq!(deserialized, value);
+}
+
+#[test]
+fn events() {
+    check(Event::Request, json!({"event": "request"}));
+
+    check(
+        Event::Cancel { reason: event::CancelReason::Runtime(RuntimeError::Timeout) },
+        json!({"event": "cancel", "reason": {"runtime": "timeout"}}),
+    );
+
+    check(
+        Event::Cancel { reason: event::CancelReason::Unknown(0x4000) },
+        json!({"event": "cancel", "reason": {"unknown": 0x4000}}),
+    );
+
+    check(
+        Event::BaseConnection { state: event::BaseState::Attached, device_type: DeviceType::Ssh, id: 7 },
+        json!({"event": "base-connection", "state": "attached", "device_type": "ssh", "id": 7}),
+    );
+
+    check(
+        Event::LatchStatus { status: event::LatchStatus::Error(HardwareError::FailedToOpen) },
+        json!({"event": "latch-status", "status": {"error": "failed-to-open"}}),
+    );
+
+    check(
+        Event::DeviceMode { mode: event::DeviceMode::Laptop },
+        json!({"event": "device-mode", "mode": "laptop"}),
+    );
+
+    check(
+        Event::DeviceMode { mode: event::DeviceMode::Unknown(5) },
+        json!({"event": "device-mode", "mode": {"unknown": 5}}),
+    );
+
+    check(
+        Event::Unknown { code: 42, data: vec![1, 2] },
+        json!({"event": "unknown", "code": 42, "data": [1, 2]}),
+    );
+}
+
+#[test]
+fn state_types() {
+    check(DeviceMode::Tablet, json!("tablet"));
+    check(DeviceMode::Studio, json!("studio"));
+
+    check(LatchStatus::Closed, json!("closed"));
+    check(LatchStatus::Error(HardwareError::FailedToRemainOpen), json!({"error": "failed-to-remain-open"}));
+    check(LatchStatus::Error(HardwareError::Unknown(3)), json!({"error": {"unknown": 3}}));
+
+    check(BaseState::NotFeasible, json!("not-feasible"));
+
+    check(DeviceType::Hid, json!("hid"));
+    check(DeviceType::Unknown(0x0c), json!({"unknown": 0x0c}));
+
+    check(
+        BaseInfo { state: BaseState::Detached, device_type: DeviceType::Ssh, id: 0x42 },
+        json!({"state": "detached", "device_type": "ssh", "id": 0x42}),
+    );
+}
+
+#[test]
+fn error_types() {
+    check(RuntimeError::NotFeasible, json!("not-feasible"));
+    check(RuntimeError::Unknown(9), json!({"unknown": 9}));
+
+    check(HardwareError::FailedToClose, json!("failed-to-close"));
+    check(HardwareError::Unknown(9), json!({"unknown": 9}));
+
+    check(CancelReason::Runtime(RuntimeError::NotFeasible), json!({"runtime": "not-feasible"}));
+    check(CancelReason::Hardware(HardwareError::FailedToOpen), json!({"hardware": "failed-to-open"}));
+}
+
+#[test]
+fn lenient_types() {
+    check(event::BaseState::Unknown(0x10), json!({"unknown": 0x10}));
+    check(event::LatchStatus::Opened, json!("opened"));
+    check(event::LatchStatus::Unknown(0x10), json!({"unknown": 0x10}));
+    check(event::CancelReason::Hardware(HardwareError::FailedToClose), json!({"hardware": "failed-to-close"}));
+}
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use sdtx::event;
+use sdtx::sim::{SimDevice, Simulator};
+use sdtx::{CancelReason, DetachSession, Error, Event, OwnedEventStream, ProtocolError, RuntimeError};
+use sdtx::{SessionAction, SessionError, SessionState};
+
+
+fn handle_next(session: &mut DetachSession, events: &mut OwnedEventStream<SimDevice>) -> SessionState {
+    let event = events.next().unwrap().unwrap();
+    session.handle(&event).unwrap()
+}
+
+fn assert_invalid(result: Result<(), SessionError>, action: SessionAction, state: SessionState) {
+    match result {
+        Err(SessionError::InvalidTransition { action: a, state: s }) => {
+            assert_eq!(a, action);
+            assert_eq!(s, state);
+        },
+        other => panic!("expected invalid transition, got {:?}", other),
+    }
+}
+
+#[test]
+fn detachment() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut events = sim.device().into_events().unwrap();
+    let mut session = DetachSession::new();
+
+    session.request(&device).unwrap();
+    assert_eq!(session.state(), SessionState::Requested);
+    assert_eq!(handle_next(&mut session, &mut events), SessionState::AwaitingConfirm);
+
+    session.heartbeat(&device).unwrap();
+    session.confirm(&device).unwrap();
+    assert_eq!(handle_next(&mut session, &mut events), SessionState::Unlocked);
+
+    // the latch stays open as long as heartbeats arrive
+    sim.advance(Duration::from_secs(4));
+    session.heartbeat(&device).unwrap();
+    sim.advance(Duration::from_secs(4));
+    assert!(sim.is_detach_in_progress());
+
+    sim.remove_base();
+    assert_eq!(handle_next(&mut session, &mut events), SessionState::Detached);
+
+    assert_invalid(session.heartbeat(&device), SessionAction::Heartbeat, SessionState::Detached);
+    assert_invalid(session.cancel(&device), SessionAction::Cancel, SessionState::Detached);
+}
+
+#[test]
+fn cancel() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut events = sim.device().into_events().unwrap();
+    let mut session = DetachSession::new();
+
+    session.request(&device).unwrap();
+    assert_eq!(handle_next(&mut session, &mut events), SessionState::AwaitingConfirm);
+
+    session.cancel(&device).unwrap();
+    assert_eq!(session.state(), SessionState::Aborted);
+    assert!(!sim.is_detach_in_progress());
+}
+
+#[test]
+fn timeout() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut events = sim.device().into_events().unwrap();
+    let mut session = DetachSession::new();
+
+    device.latch_lock().unwrap();
+
+    session.request(&device).unwrap();
+    assert_eq!(handle_next(&mut session, &mut events), SessionState::AwaitingConfirm);
+
+    sim.advance(sdtx::sim::DEFAULT_DETACH_TIMEOUT);
+
+    let reason = CancelReason::Runtime(RuntimeError::Timeout);
+    assert_eq!(handle_next(&mut session, &mut events), SessionState::Cancelled(reason));
+}
+
+#[test]
+fn heartbeat_before_request_event() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut events = sim.device().into_events().unwrap();
+    let mut session = DetachSession::new();
+
+    session.request(&device).unwrap();
+    session.heartbeat(&device).unwrap();
+    assert_eq!(session.state(), SessionState::Requested);
+
+    assert_eq!(handle_next(&mut session, &mut events), SessionState::AwaitingConfirm);
+}
+
+#[test]
+fn unknown_cancel_reason() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut session = DetachSession::new();
+
+    session.request(&device).unwrap();
+
+    let event = Event::Cancel { reason: event::CancelReason::Unknown(0x4242) };
+    match session.handle(&event) {
+        Err(SessionError::DeviceError { source: Error::ProtocolError { source } }) => {
+            assert_eq!(source, ProtocolError::InvalidCancelReason(0x4242));
+        },
+        other => panic!("expected invalid cancel reason, got {:?}", other),
+    }
+
+    assert_eq!(session.state(), SessionState::Aborted);
+    assert_eq!(session.handle(&Event::Request).unwrap(), SessionState::Aborted);
+}
+
+#[test]
+fn invalid_transitions() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut session = DetachSession::new();
+
+    assert_invalid(session.confirm(&device), SessionAction::Confirm, SessionState::Idle);
+    assert_invalid(session.heartbeat(&device), SessionAction::Heartbeat, SessionState::Idle);
+    assert_invalid(session.cancel(&device), SessionAction::Cancel, SessionState::Idle);
+
+    session.request(&device).unwrap();
+
+    assert_invalid(session.request(&device), SessionAction::Request, SessionState::Requested);
+    assert_invalid(session.confirm(&device), SessionAction::Confirm, SessionState::Requested);
+
+    // the rejected request has not been sent, which would have aborted the detachment
+    assert!(sim.is_detach_in_progress());
+}
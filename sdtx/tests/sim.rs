@@ -0,0 +1,273 @@
+use std::time::Duration;
+
+use sdtx::event::{BaseState, CancelReason, DeviceMode, LatchStatus};
+use sdtx::sim::{SimDevice, Simulator, DEFAULT_DETACH_TIMEOUT};
+use sdtx::{DeviceType, Event, HardwareError, OwnedEventStream, RuntimeError};
+
+
+fn events(sim: &Simulator) -> OwnedEventStream<SimDevice> {
+    sim.device().into_events().unwrap()
+}
+
+fn next(events: &mut OwnedEventStream<SimDevice>, n: usize) -> Vec<Event> {
+    events.take(n).collect::<Result<_, _>>().unwrap()
+}
+
+fn latch(status: LatchStatus) -> Event {
+    Event::LatchStatus { status }
+}
+
+fn cancel(reason: CancelReason) -> Event {
+    Event::Cancel { reason }
+}
+
+#[test]
+fn request_confirm_open() {
+    let sim = Simulator::new();
+    let mut events = events(&sim);
+
+    sim.press_detach_button();
+    assert!(sim.is_detach_in_progress());
+
+    sim.device().latch_confirm().unwrap();
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Opened);
+
+    sim.remove_base();
+    assert!(!sim.is_detach_in_progress());
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Closed);
+    assert_eq!(sim.device_mode(), sdtx::DeviceMode::Tablet);
+
+    assert_eq!(next(&mut events, 5), [
+        Event::Request,
+        latch(LatchStatus::Opened),
+        Event::BaseConnection { state: BaseState::Detached, device_type: DeviceType::Ssh, id: 0 },
+        Event::DeviceMode { mode: DeviceMode::Tablet },
+        latch(LatchStatus::Closed),
+    ]);
+}
+
+#[test]
+fn confirm_without_request() {
+    let sim = Simulator::new();
+
+    sim.device().latch_confirm().unwrap();
+    sim.device().latch_heartbeat().unwrap();
+    sim.device().latch_cancel().unwrap();
+
+    assert!(!sim.is_detach_in_progress());
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Closed);
+}
+
+#[test]
+fn request_not_feasible() {
+    let sim = Simulator::new();
+    let mut events = events(&sim);
+
+    sim.set_low_battery(true);
+    sim.press_detach_button();
+    assert!(!sim.is_detach_in_progress());
+
+    assert_eq!(next(&mut events, 2), [
+        Event::BaseConnection { state: BaseState::NotFeasible, device_type: DeviceType::Ssh, id: 0 },
+        cancel(CancelReason::Runtime(RuntimeError::NotFeasible)),
+    ]);
+}
+
+#[test]
+fn timeout_opens_unlocked_latch() {
+    let sim = Simulator::new();
+    let mut events = events(&sim);
+
+    sim.press_detach_button();
+    sim.advance(DEFAULT_DETACH_TIMEOUT - Duration::from_millis(1));
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Closed);
+
+    sim.advance(Duration::from_millis(1));
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Opened);
+
+    // an opened latch closes again once the timeout expires without base removal
+    sim.advance(DEFAULT_DETACH_TIMEOUT);
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Closed);
+    assert!(!sim.is_detach_in_progress());
+
+    assert_eq!(next(&mut events, 3), [
+        Event::Request,
+        latch(LatchStatus::Opened),
+        latch(LatchStatus::Closed),
+    ]);
+}
+
+#[test]
+fn timeout_cancels_locked_latch() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut events = events(&sim);
+
+    device.latch_lock().unwrap();
+    assert!(sim.is_locked());
+
+    sim.press_detach_button();
+    sim.advance(DEFAULT_DETACH_TIMEOUT);
+    assert!(!sim.is_detach_in_progress());
+
+    assert_eq!(next(&mut events, 2), [
+        Event::Request,
+        cancel(CancelReason::Runtime(RuntimeError::Timeout)),
+    ]);
+
+    device.latch_unlock().unwrap();
+    assert!(!sim.is_locked());
+}
+
+#[test]
+fn heartbeat_resets_timeout() {
+    let sim = Simulator::new();
+    let device = sim.device();
+
+    device.latch_lock().unwrap();
+    sim.set_timeout(Duration::from_secs(2));
+    sim.press_detach_button();
+
+    for _ in 0..4 {
+        sim.advance(Duration::from_secs(1));
+        device.latch_heartbeat().unwrap();
+    }
+
+    assert!(sim.is_detach_in_progress());
+
+    device.latch_confirm().unwrap();
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Opened);
+
+    for _ in 0..4 {
+        sim.advance(Duration::from_secs(1));
+        device.latch_heartbeat().unwrap();
+    }
+
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Opened);
+
+    sim.advance(Duration::from_secs(2));
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Closed);
+}
+
+#[test]
+fn confirm_unlocks_latch() {
+    let sim = Simulator::new();
+    let device = sim.device();
+
+    device.latch_lock().unwrap();
+    sim.press_detach_button();
+    device.latch_confirm().unwrap();
+
+    assert!(!sim.is_locked());
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Opened);
+}
+
+#[test]
+fn cancel_and_abort() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    let mut events = events(&sim);
+
+    // cancel before the latch opened
+    sim.press_detach_button();
+    device.latch_cancel().unwrap();
+    assert!(!sim.is_detach_in_progress());
+
+    // pressing the button again aborts the pending request
+    sim.press_detach_button();
+    sim.press_detach_button();
+    assert!(!sim.is_detach_in_progress());
+
+    // cancel after the latch opened closes it again
+    sim.press_detach_button();
+    device.latch_confirm().unwrap();
+    device.latch_cancel().unwrap();
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Closed);
+    assert!(!sim.is_detach_in_progress());
+
+    assert_eq!(next(&mut events, 8), [
+        Event::Request,
+        Event::Request,
+        Event::Request,
+        Event::Request,
+        Event::Request,
+        latch(LatchStatus::Opened),
+        Event::Request,
+        latch(LatchStatus::Closed),
+    ]);
+}
+
+#[test]
+fn injected_faults() {
+    let faults = [
+        (HardwareError::FailedToOpen, vec![]),
+        (HardwareError::FailedToRemainOpen, vec![latch(LatchStatus::Opened)]),
+    ];
+
+    for (error, mut expected) in faults {
+        let sim = Simulator::new();
+        let mut events = events(&sim);
+
+        sim.fail_latch(error);
+        sim.press_detach_button();
+        sim.device().latch_confirm().unwrap();
+
+        assert!(!sim.is_detach_in_progress());
+        assert_eq!(sim.latch_status(), sdtx::LatchStatus::Error(error));
+
+        expected.insert(0, Event::Request);
+        expected.push(latch(LatchStatus::Error(error)));
+        expected.push(cancel(CancelReason::Hardware(error)));
+
+        assert_eq!(next(&mut events, expected.len()), expected);
+    }
+
+    let sim = Simulator::new();
+    let mut events = events(&sim);
+
+    sim.press_detach_button();
+    sim.device().latch_confirm().unwrap();
+    sim.fail_latch(HardwareError::FailedToClose);
+    sim.device().latch_cancel().unwrap();
+
+    let error = HardwareError::FailedToClose;
+    assert_eq!(sim.latch_status(), sdtx::LatchStatus::Error(error));
+
+    assert_eq!(next(&mut events, 5), [
+        Event::Request,
+        latch(LatchStatus::Opened),
+        Event::Request,
+        latch(LatchStatus::Error(error)),
+        cancel(CancelReason::Hardware(error)),
+    ]);
+}
+
+#[test]
+fn shutdown() {
+    let sim = Simulator::new();
+    let device = sim.device();
+
+    sim.shutdown();
+
+    assert!(matches!(device.latch_request(), Err(sdtx::Error::DeviceShutdown { .. })));
+    assert!(device.into_events().is_err());
+}
+
+#[test]
+fn nonblocking_read() {
+    let sim = Simulator::new();
+    let device = sim.device();
+    device.set_nonblocking(true).unwrap();
+
+    let mut events = device.into_events().unwrap();
+    let mut batch = Vec::new();
+
+    assert_eq!(events.try_read_events(&mut batch).unwrap(), 0);
+
+    sim.press_detach_button();
+    sim.set_device_mode(sdtx::DeviceMode::Tablet);
+
+    assert_eq!(events.try_read_events(&mut batch).unwrap(), 2);
+    assert_eq!(batch, [Event::Request, Event::DeviceMode { mode: DeviceMode::Tablet }]);
+    assert_eq!(events.try_read_events(&mut batch).unwrap(), 0);
+}
@@ -0,0 +1,70 @@
+use sdtx::sim::Simulator;
+use sdtx::{BaseInfo, BaseState, CancelReason, DeviceMode, DeviceState, HardwareError, LatchStatus, StateChange};
+
+
+fn track(sim: &Simulator, num_events: usize, actions: impl FnOnce()) -> (DeviceState, Vec<StateChange>) {
+    let device = sim.device();
+    let mut state = DeviceState::query(&device).unwrap();
+    let events = device.into_events().unwrap();
+
+    actions();
+
+    let changes = events.take(num_events)
+        .flat_map(|event| state.apply(&event.unwrap()))
+        .collect();
+
+    (state, changes)
+}
+
+#[test]
+fn detachment() {
+    let sim = Simulator::new();
+    let base = sim.base_info();
+
+    let (state, changes) = track(&sim, 5, || {
+        sim.press_detach_button();
+        sim.device().latch_confirm().unwrap();
+        sim.remove_base();
+    });
+
+    let detached = BaseInfo { state: BaseState::Detached, ..base };
+
+    assert_eq!(changes, [
+        StateChange::DetachPending { old: false, new: true },
+        StateChange::LatchStatus { old: LatchStatus::Closed, new: LatchStatus::Opened },
+        StateChange::Base { old: base, new: detached },
+        StateChange::DetachPending { old: true, new: false },
+        StateChange::DeviceMode { old: DeviceMode::Laptop, new: DeviceMode::Tablet },
+        StateChange::LatchStatus { old: LatchStatus::Opened, new: LatchStatus::Closed },
+    ]);
+
+    assert_eq!(state, DeviceState {
+        base: detached,
+        device_mode: DeviceMode::Tablet,
+        latch_status: LatchStatus::Closed,
+        detach_pending: false,
+        last_cancel: None,
+    });
+}
+
+#[test]
+fn hardware_failure() {
+    let sim = Simulator::new();
+    let error = HardwareError::FailedToOpen;
+
+    let (state, changes) = track(&sim, 3, || {
+        sim.fail_latch(error);
+        sim.press_detach_button();
+        sim.device().latch_confirm().unwrap();
+    });
+
+    assert_eq!(changes, [
+        StateChange::DetachPending { old: false, new: true },
+        StateChange::LatchStatus { old: LatchStatus::Closed, new: LatchStatus::Error(error) },
+        StateChange::DetachPending { old: true, new: false },
+        StateChange::Cancel { reason: CancelReason::Hardware(error) },
+    ]);
+
+    assert_eq!(state.last_cancel, Some(CancelReason::Hardware(error)));
+    assert!(!state.detach_pending);
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+
+use sdtx::capture::{Record, Replay};
+use sdtx::event::{DeviceMode, TimestampedEvent};
+use sdtx::{Device, Event};
+
+
+const EVENTS: [Event; 2] = [Event::Request, Event::DeviceMode { mode: DeviceMode::Tablet }];
+
+fn replay() -> Device<Replay> {
+    let records = EVENTS.iter().map(|event| Record::Read {
+        time: Default::default(),
+        data: event.to_bytes(),
+    });
+
+    Device::from(Replay::new(records))
+}
+
+fn check(stamped: &[TimestampedEvent]) {
+    let events: Vec<_> = stamped.iter().map(|e| e.event.clone()).collect();
+    assert_eq!(events, EVENTS);
+
+    assert!(stamped[1].received_at >= stamped[0].received_at);
+    assert_eq!(stamped[0].duration_since(&stamped[1]), Duration::ZERO);
+}
+
+#[test]
+fn iterator() {
+    let mut device = replay();
+
+    let stamped = device.events().unwrap()
+        .timestamped()
+        .take(2)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    check(&stamped);
+}
+
+#[test]
+fn stream() {
+    let mut device = replay();
+    let events = device.events_async().unwrap().timestamped();
+
+    let stamped = futures::executor::block_on(events.take(2).collect::<Vec<_>>())
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    check(&stamped);
+}
+
+/// Replay the first two events in a single read, followed by a read of the
+/// third one.
+fn replay_batched() -> Device<Replay> {
+    let mut batch = EVENTS[0].to_bytes();
+    batch.extend(EVENTS[1].to_bytes());
+
+    let records = vec![
+        Record::Read { time: Default::default(), data: batch },
+        Record::Read { time: Default::default(), data: Event::Request.to_bytes() },
+    ];
+
+    Device::from(Replay::new(records))
+}
+
+#[test]
+fn stamped_when_read() {
+    let delay = Duration::from_millis(20);
+
+    let mut device = replay_batched();
+    let mut events = device.events().unwrap().timestamped();
+
+    let first = events.next().unwrap().unwrap();
+    std::thread::sleep(delay);
+    let second = events.next().unwrap().unwrap();
+    let third = events.next().unwrap().unwrap();
+
+    assert_eq!(second.received_at, first.received_at);
+    assert!(third.duration_since(&second) >= delay);
+}
+
+#[test]
+fn stream_stamped_when_read() {
+    let delay = Duration::from_millis(20);
+
+    let mut device = replay_batched();
+    let mut events = device.events_async().unwrap().timestamped();
+
+    futures::executor::block_on(async {
+        let first = events.next().await.unwrap().unwrap();
+        std::thread::sleep(delay);
+        let second = events.next().await.unwrap().unwrap();
+        let third = events.next().await.unwrap().unwrap();
+
+        assert_eq!(second.received_at, first.received_at);
+        assert!(third.duration_since(&second) >= delay);
+    });
+}